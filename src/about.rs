@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use comrak::{markdown_to_html, ComrakOptions};
+
+use html_builder::*;
+
+use serde::Deserialize;
+
+use super::images::{Backgrounds, ResponsiveImage};
+use super::site::SiteConfig;
+use super::{escape_html, Page, CSS};
+
+/// Struct that represents a milestone of the timeline of the "Who am I?"
+/// page: what happened, in which year, where, and where to read about it.
+#[derive(Debug, Deserialize)]
+struct Milestone {
+    year: i32,
+    title: String,
+    place: Option<String>,
+    link: Option<String>,
+}
+
+/// Struct that represents the file of the timeline, as a list of `[[milestone]]`
+/// tables.
+#[derive(Debug, Default, Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    milestone: Vec<Milestone>,
+}
+
+impl Timeline {
+    /// Reads the milestones in `timeline_path`, if the file exists, from the
+    /// earliest to the latest.
+    fn from_file(timeline_path: &str) -> Result<Self, &'static str> {
+        if !Path::new(timeline_path).exists() {
+            return Ok(Self::default());
+        }
+
+        println!("Reading timeline file {:?}", timeline_path);
+        let mut timeline: Self = match fs::read_to_string(timeline_path) {
+            Ok(t) => match toml::from_str(&t) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("{}", e);
+                    return Err("Couldn't parse timeline file.");
+                }
+            },
+            _ => return Err("Couldn't read timeline file."),
+        };
+        timeline.milestone.sort_by_key(|m| m.year);
+
+        Ok(timeline)
+    }
+}
+
+/// Builds the "Who am I?" page from the biography in `about_path`, written in
+/// markdown, followed by the timeline in `timeline_path`, when there is one.
+pub(crate) fn build_who_am_i(
+    about_path: &str,
+    timeline_path: &str,
+    config: &SiteConfig,
+    backgrounds: &Backgrounds,
+    profile_pic: &ResponsiveImage,
+) -> Result<(), Box<dyn Error>> {
+    println!("Reading biography file {:?}", about_path);
+    let biography = match fs::read_to_string(about_path) {
+        Ok(b) => b,
+        _ => return Err("Couldn't read biography file.".into()),
+    };
+    let timeline = Timeline::from_file(timeline_path)?;
+
+    let mut page = Page::new("Who am I?", CSS::Science, config, backgrounds);
+    page.add_site_top_bar(profile_pic, Some("Who am I?"));
+
+    {
+        let mut container = page
+            .buf
+            .div()
+            .attr("class='blank-container page-ultra-wide-treatment about'");
+        write!(
+            container.div().attr("class='about-biography'"),
+            "{}",
+            markdown_to_html(&biography, &ComrakOptions::default())
+        )?;
+
+        if !timeline.milestone.is_empty() {
+            write!(container.h2(), "Timeline")?;
+            let mut list = container.div().attr("class='timeline about-timeline'");
+            for milestone in &timeline.milestone {
+                let mut item = list.div().attr("class='timeline-item'");
+                let mut left = item.div().attr("class='timeline-left'");
+                match &milestone.link {
+                    Some(link) => left.a().attr(
+                        format!("class='timeline-icon icon-lg' href='{}'", escape_html(link))
+                            .as_str(),
+                    ),
+                    None => left.span().attr("class='timeline-icon'"),
+                };
+
+                let mut content = item.div().attr("class='timeline-content'");
+                write!(
+                    content.span().attr("class='timeline-year'"),
+                    "{}",
+                    milestone.year
+                )?;
+                let mut title = content.p().attr("class='no_margin'");
+                match &milestone.link {
+                    Some(link) => write!(
+                        title,
+                        "<b><a href='{}'>{}</a></b>",
+                        escape_html(link),
+                        escape_html(&milestone.title)
+                    )?,
+                    None => write!(title, "<b>{}</b>", escape_html(&milestone.title))?,
+                }
+                if let Some(place) = &milestone.place {
+                    write!(
+                        content.p().attr("class='no_margin text-gray'"),
+                        "{}",
+                        escape_html(place)
+                    )?;
+                }
+            }
+        }
+    }
+
+    page.add_footer();
+    page.publish("who-am-i.html");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeline_sorted_by_year() {
+        let folder = std::env::temp_dir().join("personal-website-timeline");
+        fs::create_dir_all(&folder).unwrap();
+        let timeline_path = folder.join("timeline.toml");
+        fs::write(
+            &timeline_path,
+            r#"
+[[milestone]]
+year = 2022
+title = "Started the PhD"
+place = "University of Porto"
+
+[[milestone]]
+year = 2017
+title = "Started the degree"
+
+[[milestone]]
+year = 2020
+title = "Published a paper"
+link = "publications.html"
+"#,
+        )
+        .unwrap();
+
+        let timeline = Timeline::from_file(timeline_path.to_str().unwrap()).unwrap();
+        let years = timeline
+            .milestone
+            .iter()
+            .map(|m| m.year)
+            .collect::<Vec<_>>();
+        assert_eq!(years, [2017, 2020, 2022]);
+        assert_eq!(
+            timeline.milestone[1].link.as_deref(),
+            Some("publications.html")
+        );
+        assert_eq!(
+            timeline.milestone[2].place.as_deref(),
+            Some("University of Porto")
+        );
+
+        fs::write(&timeline_path, "[[milestone]]\nyear = \"soon\"\n").unwrap();
+        assert!(Timeline::from_file(timeline_path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn timeline_from_missing_file() {
+        let timeline = Timeline::from_file("missing/timeline.toml").unwrap();
+        assert!(timeline.milestone.is_empty());
+    }
+}
@@ -0,0 +1,1102 @@
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use super::escape_html;
+use super::table::DataTable;
+
+const CHART_WIDTH: f64 = 800.;
+const CHART_HEIGHT: f64 = 500.;
+const CHART_FONT: &str = "Atkinson Hyperlegible, sans-serif";
+const CHART_TEXT_COLOR: &str = "#3b4351";
+const CHART_GRID_COLOR: &str = "#dadee4";
+
+/// Colors of the series of a chart, starting with the greens of the site.
+const CHART_PALETTE: [&str; 8] = [
+    "#4FBA6D", "#282842", "#92D4AB", "#E0A458", "#5B8E7D", "#C3423F", "#7D8CC4", "#B8B8D1",
+];
+
+/// Enum to enumerate the kinds of chart that can be declared in a manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ChartKind {
+    Bar,
+    StackedBar,
+    Violin,
+    Scatter,
+    Treemap,
+}
+
+/// Struct that represents a chart declared in a manifest, drawn from the
+/// columns of a tidy table, in any format study tables are read from (one
+/// observation per row).
+///
+/// `x` holds the categories (or the x values of a scatter plot), `y` the
+/// values, which are counted when missing, and `color` splits the data into
+/// series. Treemaps nest the columns in `path` instead.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ChartSpec {
+    kind: ChartKind,
+    pub(crate) data: String,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    path: Vec<String>,
+    #[serde(default)]
+    normalize: bool,
+    #[serde(default)]
+    x_title: Option<String>,
+    #[serde(default)]
+    y_title: Option<String>,
+}
+
+/// Series of values of a bar chart, one value per category.
+#[derive(Debug)]
+pub(crate) struct BarSeries {
+    pub(crate) name: String,
+    pub(crate) values: Vec<f64>,
+}
+
+/// Node of a treemap, whose value is the sum of its children when it has any.
+#[derive(Debug)]
+pub(crate) struct TreeNode {
+    pub(crate) label: String,
+    pub(crate) value: f64,
+    pub(crate) children: Vec<TreeNode>,
+}
+
+/// Enum that represents the data of a chart, independently of where it was
+/// read from, ready to be rendered to SVG.
+#[derive(Debug)]
+pub(crate) enum ChartData {
+    Bar {
+        categories: Vec<String>,
+        series: Vec<BarSeries>,
+        stacked: bool,
+    },
+    Violin {
+        groups: Vec<(String, Vec<f64>)>,
+    },
+    Scatter {
+        series: Vec<(String, Vec<(f64, f64)>)>,
+    },
+    Treemap {
+        roots: Vec<TreeNode>,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct Chart {
+    pub(crate) data: ChartData,
+    pub(crate) x_title: String,
+    pub(crate) y_title: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+impl Chart {
+    pub(crate) fn from_spec(spec: &ChartSpec) -> Result<Self, &'static str> {
+        let table = DataTable::from_file(&spec.data)?;
+        let column = |name: &Option<String>| -> Result<Option<Vec<&str>>, &'static str> {
+            match name {
+                Some(n) => match table.column(n) {
+                    Some(c) => Ok(Some(c)),
+                    _ => Err("Couldn't find chart column in data."),
+                },
+                None => Ok(None),
+            }
+        };
+        let x = column(&spec.x)?;
+        let y = column(&spec.y)?;
+        let color = column(&spec.color)?;
+        // Values that aren't finite numbers, such as "NaN", count as missing.
+        let numbers = |c: &[&str]| {
+            c.iter()
+                .map(|v| v.parse::<f64>().ok().filter(|v| v.is_finite()))
+                .collect::<Vec<_>>()
+        };
+
+        let data = match spec.kind {
+            ChartKind::Bar | ChartKind::StackedBar => {
+                let x = match x {
+                    Some(x) => x,
+                    _ => return Err("Bar charts need an x column."),
+                };
+                let y = y.map(|y| numbers(&y));
+                let categories = unique(&x);
+                let names = match &color {
+                    Some(c) => unique(c),
+                    None => vec![spec.y.clone().unwrap_or_else(|| String::from("Count"))],
+                };
+
+                let mut series = names
+                    .into_iter()
+                    .map(|name| BarSeries {
+                        name,
+                        values: vec![0.; categories.len()],
+                    })
+                    .collect::<Vec<_>>();
+                for (row, category) in x.iter().enumerate() {
+                    let c = categories.iter().position(|c| c == category).unwrap();
+                    let s = match &color {
+                        Some(color) => series.iter().position(|s| s.name == color[row]).unwrap(),
+                        None => 0,
+                    };
+                    series[s].values[c] += match &y {
+                        Some(y) => y[row].unwrap_or(0.),
+                        None => 1.,
+                    };
+                }
+
+                // Proportions of each category instead of absolute values.
+                if spec.normalize {
+                    for c in 0..categories.len() {
+                        let total: f64 = series.iter().map(|s| s.values[c]).sum();
+                        if total > 0. {
+                            for s in series.iter_mut() {
+                                s.values[c] /= total;
+                            }
+                        }
+                    }
+                }
+
+                ChartData::Bar {
+                    categories,
+                    series,
+                    stacked: spec.kind == ChartKind::StackedBar,
+                }
+            }
+            ChartKind::Violin => {
+                let (x, y) = match (x, y) {
+                    (Some(x), Some(y)) => (x, numbers(&y)),
+                    _ => return Err("Violin charts need x and y columns."),
+                };
+                let groups = unique(&x)
+                    .into_iter()
+                    .map(|group| {
+                        let values = x
+                            .iter()
+                            .zip(&y)
+                            .filter(|(g, _)| **g == group)
+                            .filter_map(|(_, v)| *v)
+                            .collect();
+                        (group, values)
+                    })
+                    .collect();
+                ChartData::Violin { groups }
+            }
+            ChartKind::Scatter => {
+                let (x, y) = match (x, y) {
+                    (Some(x), Some(y)) => (numbers(&x), numbers(&y)),
+                    _ => return Err("Scatter charts need x and y columns."),
+                };
+                let names = match &color {
+                    Some(c) => unique(c),
+                    None => vec![String::new()],
+                };
+                let series = names
+                    .into_iter()
+                    .map(|name| {
+                        let points = (0..x.len())
+                            .filter(|&row| match &color {
+                                Some(c) => c[row] == name,
+                                None => true,
+                            })
+                            .filter_map(|row| Some((x[row]?, y[row]?)))
+                            .collect();
+                        (name, points)
+                    })
+                    .collect();
+                ChartData::Scatter { series }
+            }
+            ChartKind::Treemap => {
+                if spec.path.is_empty() {
+                    return Err("Treemap charts need a path of columns.");
+                }
+                let mut levels = Vec::new();
+                for name in &spec.path {
+                    match table.column(name) {
+                        Some(c) => levels.push(c),
+                        _ => return Err("Couldn't find chart column in data."),
+                    }
+                }
+                let y = y.map(|y| numbers(&y));
+
+                let mut roots = Vec::<TreeNode>::new();
+                for row in 0..levels[0].len() {
+                    let value = match &y {
+                        Some(y) => y[row].unwrap_or(0.),
+                        None => 1.,
+                    };
+                    let mut nodes = &mut roots;
+                    for level in &levels {
+                        let label = level[row];
+                        if label.is_empty() {
+                            break;
+                        }
+                        let i = match nodes.iter().position(|n| n.label == label) {
+                            Some(i) => i,
+                            None => {
+                                nodes.push(TreeNode {
+                                    label: label.to_string(),
+                                    value: 0.,
+                                    children: Vec::new(),
+                                });
+                                nodes.len() - 1
+                            }
+                        };
+                        nodes[i].value += value;
+                        nodes = &mut nodes[i].children;
+                    }
+                }
+                ChartData::Treemap { roots }
+            }
+        };
+
+        Ok(Self {
+            data,
+            x_title: spec.x_title.clone().or_else(|| spec.x.clone()).unwrap_or_default(),
+            y_title: spec.y_title.clone().or_else(|| spec.y.clone()).unwrap_or_default(),
+        })
+    }
+
+    /// Renders the chart to a standalone SVG document, which can also be
+    /// inlined in a page.
+    pub(crate) fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        write!(
+            svg,
+            "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 {} {}' width='100%' \
+            font-family='{}' font-size='13' fill='{}' role='img'>",
+            CHART_WIDTH, CHART_HEIGHT, CHART_FONT, CHART_TEXT_COLOR
+        )
+        .unwrap();
+        write!(svg, "<title>{}</title>", escape_html(&self.label())).unwrap();
+
+        match &self.data {
+            ChartData::Bar {
+                categories,
+                series,
+                stacked,
+            } => self.write_bars(&mut svg, categories, series, *stacked),
+            ChartData::Violin { groups } => self.write_violins(&mut svg, groups),
+            ChartData::Scatter { series } => self.write_scatter(&mut svg, series),
+            ChartData::Treemap { roots } => write_treemap(
+                &mut svg,
+                roots,
+                Rect {
+                    x: 10.,
+                    y: 10.,
+                    w: CHART_WIDTH - 20.,
+                    h: CHART_HEIGHT - 20.,
+                },
+                None,
+            ),
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// Short name of the chart, e.g. "Bar chart of Count by Feature ID".
+    pub(crate) fn label(&self) -> String {
+        let axes = |kind: &str, joint: &str| match (self.y_title.as_str(), self.x_title.as_str()) {
+            ("", "") => kind.to_string(),
+            (y, "") => format!("{} of {}", kind, y),
+            ("", x) => format!("{} by {}", kind, x),
+            (y, x) => format!("{} of {} {} {}", kind, y, joint, x),
+        };
+        match &self.data {
+            ChartData::Bar { .. } => axes("Bar chart", "by"),
+            ChartData::Violin { .. } => axes("Violin plot", "by"),
+            ChartData::Scatter { .. } => axes("Scatter plot", "against"),
+            ChartData::Treemap { .. } => String::from("Treemap"),
+        }
+    }
+
+    /// Describes the data of the chart in a few sentences: the total of every
+    /// category, the spread of every group, or the range of every axis.
+    pub(crate) fn summary(&self) -> String {
+        match &self.data {
+            ChartData::Bar { categories, series, .. } => {
+                let totals = categories
+                    .iter()
+                    .enumerate()
+                    .map(|(c, category)| {
+                        let total = format_number(series.iter().map(|s| s.values[c]).sum());
+                        if series.len() == 1 {
+                            return format!("{}: {}", category, total);
+                        }
+                        let parts = series
+                            .iter()
+                            .filter(|s| s.values[c] != 0.)
+                            .map(|s| format!("{} {}", s.name, format_number(s.values[c])))
+                            .collect::<Vec<_>>();
+                        format!("{}: {} ({})", category, total, parts.join(", "))
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{} categories, {} series. {}.",
+                    categories.len(),
+                    series.len(),
+                    totals.join("; ")
+                )
+            }
+            ChartData::Violin { groups } => {
+                let spreads = groups
+                    .iter()
+                    .filter(|(_, values)| !values.is_empty())
+                    .map(|(group, values)| {
+                        let (min, max) = min_max(values.iter().copied());
+                        format!(
+                            "{}: {} values, min {}, median {}, max {}",
+                            group,
+                            values.len(),
+                            format_number(min),
+                            format_number(median(values)),
+                            format_number(max)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!("{} groups. {}.", groups.len(), spreads.join("; "))
+            }
+            ChartData::Scatter { series } => {
+                let points = series.iter().flat_map(|(_, points)| points).collect::<Vec<_>>();
+                if points.is_empty() {
+                    return String::from("No points.");
+                }
+                let (x_min, x_max) = min_max(points.iter().map(|p| p.0));
+                let (y_min, y_max) = min_max(points.iter().map(|p| p.1));
+                format!(
+                    "{} points. {} ranges from {} to {}, and {} from {} to {}.",
+                    points.len(),
+                    if self.x_title.is_empty() { "X" } else { &self.x_title },
+                    format_number(x_min),
+                    format_number(x_max),
+                    if self.y_title.is_empty() { "Y" } else { &self.y_title },
+                    format_number(y_min),
+                    format_number(y_max)
+                )
+            }
+            ChartData::Treemap { roots } => {
+                // A single root, such as "All", says nothing about the data.
+                let mut nodes = roots;
+                while nodes.len() == 1 && !nodes[0].children.is_empty() {
+                    nodes = &nodes[0].children;
+                }
+                let mut largest = nodes.iter().collect::<Vec<_>>();
+                largest.sort_by(|a, b| b.value.total_cmp(&a.value));
+                let listed = largest
+                    .iter()
+                    .take(5)
+                    .map(|n| format!("{}: {}", n.label, format_number(n.value)))
+                    .collect::<Vec<_>>();
+                format!(
+                    "{} categories, with a total of {}. Largest: {}.",
+                    nodes.len(),
+                    format_number(nodes.iter().map(|n| n.value).sum()),
+                    listed.join("; ")
+                )
+            }
+        }
+    }
+
+    /// Lays out the data the chart is drawn from as a table, with the
+    /// category, group or path of every value as its first column.
+    pub(crate) fn to_data_table(&self) -> DataTable {
+        let x_title = if self.x_title.is_empty() { "Category" } else { &self.x_title };
+        let y_title = if self.y_title.is_empty() { "Value" } else { &self.y_title };
+
+        match &self.data {
+            ChartData::Bar { categories, series, .. } => DataTable::from_columns(
+                std::iter::once(x_title.to_string())
+                    .chain(series.iter().map(|s| s.name.clone()))
+                    .collect(),
+                categories
+                    .iter()
+                    .enumerate()
+                    .map(|(c, category)| {
+                        std::iter::once(category.clone())
+                            .chain(series.iter().map(|s| format_number(s.values[c])))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            ChartData::Violin { groups } => DataTable::from_columns(
+                [x_title, "Values", "Min", "Median", "Max"]
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+                groups
+                    .iter()
+                    .filter(|(_, values)| !values.is_empty())
+                    .map(|(group, values)| {
+                        let (min, max) = min_max(values.iter().copied());
+                        vec![
+                            group.clone(),
+                            values.len().to_string(),
+                            format_number(min),
+                            format_number(median(values)),
+                            format_number(max),
+                        ]
+                    })
+                    .collect(),
+            ),
+            ChartData::Scatter { series } => DataTable::from_columns(
+                vec![String::from("Series"), x_title.to_string(), y_title.to_string()],
+                series
+                    .iter()
+                    .flat_map(|(name, points)| {
+                        points
+                            .iter()
+                            .map(move |(x, y)| vec![name.clone(), format_number(*x), format_number(*y)])
+                    })
+                    .collect(),
+            ),
+            ChartData::Treemap { roots } => {
+                let mut rows = Vec::new();
+                tree_rows(roots, "", &mut rows);
+                DataTable::from_columns(vec![String::from("Path"), String::from("Value")], rows)
+            }
+        }
+    }
+
+    /// Area left for the data once the axes, and the legend when there is
+    /// more than one series, are drawn.
+    fn plot_area(&self, legend: bool, rotate_labels: bool) -> Rect {
+        let top = if legend { 50. } else { 20. };
+        let bottom = if rotate_labels { 130. } else { 60. };
+        Rect {
+            x: 80.,
+            y: top,
+            w: CHART_WIDTH - 100.,
+            h: CHART_HEIGHT - top - bottom,
+        }
+    }
+
+    fn write_bars(&self, svg: &mut String, categories: &[String], series: &[BarSeries], stacked: bool) {
+        // Negative values are drawn down from the zero line, and stacked
+        // ones stack below it, apart from the positive ones.
+        let (min, max) = (0..categories.len())
+            .map(|c| {
+                let values = series.iter().map(|s| s.values[c]);
+                if stacked {
+                    (values.clone().filter(|v| *v < 0.).sum(), values.filter(|v| *v > 0.).sum())
+                } else {
+                    (values.clone().fold(0., f64::min), values.fold(0., f64::max))
+                }
+            })
+            .fold((0., 0.), |(min, max), (low, high)| (f64::min(min, low), f64::max(max, high)));
+        let rotate_labels = rotate_category_labels(categories);
+        let area = self.plot_area(series.len() > 1, rotate_labels);
+        let ticks = nice_ticks(min, max);
+        let (low, high) = (ticks[0], ticks[ticks.len() - 1]);
+        let scale = |v: f64| area.y + area.h - (v - low) / (high - low) * area.h;
+
+        write_value_axis(svg, area, &ticks, &self.y_title);
+        write_category_axis(svg, area, categories, rotate_labels, &self.x_title);
+        if series.len() > 1 {
+            write_legend(svg, series.iter().map(|s| s.name.as_str()));
+        }
+
+        let band = area.w / categories.len().max(1) as f64;
+        for (c, category) in categories.iter().enumerate() {
+            let (mut positive_base, mut negative_base) = (0., 0.);
+            for (i, s) in series.iter().enumerate() {
+                let value = s.values[c];
+                let (x, w, from) = if stacked {
+                    let base = if value < 0. { &mut negative_base } else { &mut positive_base };
+                    let from = *base;
+                    *base += value;
+                    (area.x + band * (c as f64 + 0.15), band * 0.7, from)
+                } else {
+                    let w = band * 0.7 / series.len() as f64;
+                    (area.x + band * (c as f64 + 0.15) + w * i as f64, w, 0.)
+                };
+                let (bottom, top) = if value < 0. { (from + value, from) } else { (from, from + value) };
+                write!(
+                    svg,
+                    "<rect x='{:.1}' y='{:.1}' width='{:.1}' height='{:.1}' fill='{}'><title>{}: {}</title></rect>",
+                    x,
+                    scale(top),
+                    w,
+                    scale(bottom) - scale(top),
+                    CHART_PALETTE[i % CHART_PALETTE.len()],
+                    escape_html(&format!("{} ({})", category, s.name)),
+                    format_number(value)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn write_violins(&self, svg: &mut String, groups: &[(String, Vec<f64>)]) {
+        let values = groups.iter().flat_map(|(_, v)| v.iter().copied());
+        let (min, max) = values.fold((f64::MAX, f64::MIN), |(a, b), v| (a.min(v), b.max(v)));
+        let (min, max) = if min > max { (0., 1.) } else { (min, max) };
+        let categories = groups.iter().map(|(g, _)| g.clone()).collect::<Vec<_>>();
+        let rotate_labels = rotate_category_labels(&categories);
+        let area = self.plot_area(false, rotate_labels);
+        let ticks = nice_ticks(min, max);
+        let (low, high) = (ticks[0], ticks[ticks.len() - 1]);
+        let scale = |v: f64| area.y + area.h - (v - low) / (high - low) * area.h;
+
+        write_value_axis(svg, area, &ticks, &self.y_title);
+        write_category_axis(svg, area, &categories, rotate_labels, &self.x_title);
+
+        let band = area.w / groups.len().max(1) as f64;
+        for (g, (_, values)) in groups.iter().enumerate() {
+            if values.is_empty() {
+                continue;
+            }
+            let center = area.x + band * (g as f64 + 0.5);
+            let color = CHART_PALETTE[g % CHART_PALETTE.len()];
+            let density = kernel_density(values, 60);
+            let peak = density.iter().map(|(_, d)| *d).fold(0., f64::max);
+
+            if peak > 0. {
+                let half_width = |d: f64| d / peak * band * 0.4;
+                let mut path = String::new();
+                for (i, (v, d)) in density.iter().enumerate() {
+                    write!(path, "{}{:.1},{:.1} ", if i == 0 { 'M' } else { 'L' }, center + half_width(*d), scale(*v)).unwrap();
+                }
+                for (v, d) in density.iter().rev() {
+                    write!(path, "L{:.1},{:.1} ", center - half_width(*d), scale(*v)).unwrap();
+                }
+                write!(
+                    svg,
+                    "<path d='{}Z' fill='{}' fill-opacity='0.5' stroke='{}'/>",
+                    path, color, color
+                )
+                .unwrap();
+            }
+
+            let median = median(values);
+            write!(
+                svg,
+                "<line x1='{:.1}' x2='{:.1}' y1='{:.1}' y2='{:.1}' stroke='black' stroke-dasharray='4 3'><title>Median: {}</title></line>",
+                center - band * 0.3,
+                center + band * 0.3,
+                scale(median),
+                scale(median),
+                format_number(median)
+            )
+            .unwrap();
+            for v in values {
+                write!(
+                    svg,
+                    "<circle cx='{:.1}' cy='{:.1}' r='2' fill='{}' fill-opacity='0.6'/>",
+                    center,
+                    scale(*v),
+                    CHART_PALETTE[1]
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn write_scatter(&self, svg: &mut String, series: &[(String, Vec<(f64, f64)>)]) {
+        let points = series.iter().flat_map(|(_, p)| p.iter().copied());
+        let (x_min, x_max, y_min, y_max) = points.fold(
+            (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+            |(a, b, c, d), (x, y)| (a.min(x), b.max(x), c.min(y), d.max(y)),
+        );
+        let (x_min, x_max) = if x_min > x_max { (0., 1.) } else { (x_min, x_max) };
+        let (y_min, y_max) = if y_min > y_max { (0., 1.) } else { (y_min, y_max) };
+        let legend = series.len() > 1;
+        let area = self.plot_area(legend, false);
+        let x_ticks = nice_ticks(x_min, x_max);
+        let y_ticks = nice_ticks(y_min, y_max);
+        let (x_low, x_high) = (x_ticks[0], x_ticks[x_ticks.len() - 1]);
+        let (y_low, y_high) = (y_ticks[0], y_ticks[y_ticks.len() - 1]);
+        let scale_x = |v: f64| area.x + (v - x_low) / (x_high - x_low) * area.w;
+        let scale_y = |v: f64| area.y + area.h - (v - y_low) / (y_high - y_low) * area.h;
+
+        write_value_axis(svg, area, &y_ticks, &self.y_title);
+        for tick in &x_ticks {
+            write!(
+                svg,
+                "<text x='{:.1}' y='{:.1}' text-anchor='middle'>{}</text>",
+                scale_x(*tick),
+                area.y + area.h + 20.,
+                format_number(*tick)
+            )
+            .unwrap();
+        }
+        write_axis_title(svg, area, &self.x_title, 45.);
+        if legend {
+            write_legend(svg, series.iter().map(|(name, _)| name.as_str()));
+        }
+
+        for (i, (name, points)) in series.iter().enumerate() {
+            for (x, y) in points {
+                write!(
+                    svg,
+                    "<circle cx='{:.1}' cy='{:.1}' r='4' fill='{}' fill-opacity='0.8'><title>{}({}, {})</title></circle>",
+                    scale_x(*x),
+                    scale_y(*y),
+                    CHART_PALETTE[i % CHART_PALETTE.len()],
+                    if name.is_empty() { String::new() } else { format!("{}: ", escape_html(name)) },
+                    format_number(*x),
+                    format_number(*y)
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the distinct values of a column, in order of appearance.
+/// Distinct values of `column`, in the order they first appear, or in
+/// ascending order when they are all numbers, such as years.
+fn unique(column: &[&str]) -> Vec<String> {
+    let mut values = Vec::<String>::new();
+    for v in column {
+        if !values.iter().any(|u| u == v) {
+            values.push(v.to_string());
+        }
+    }
+    let numbers = values.iter().map(|v| v.parse::<f64>()).collect::<Result<Vec<_>, _>>();
+    if let Ok(numbers) = numbers {
+        let mut sorted = numbers.into_iter().zip(values).collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        values = sorted.into_iter().map(|(_, v)| v).collect();
+    }
+    values
+}
+
+fn rotate_category_labels(categories: &[String]) -> bool {
+    categories.len() > 6 || categories.iter().any(|c| c.chars().count() > 10)
+}
+
+/// Returns round tick values that cover the range from `min` to `max`.
+fn nice_ticks(min: f64, max: f64) -> Vec<f64> {
+    let (min, max) = if max > min { (min, max) } else { (min - 1., min + 1.) };
+    let rough_step = (max - min) / 5.;
+    let magnitude = 10f64.powf(rough_step.log10().floor());
+    let step = magnitude
+        * match rough_step / magnitude {
+            r if r > 5. => 10.,
+            r if r > 2. => 5.,
+            r if r > 1. => 2.,
+            _ => 1.,
+        };
+
+    let first = (min / step).floor() as i64;
+    let last = (max / step).ceil() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+fn format_number(v: f64) -> String {
+    if v.fract() == 0. {
+        format!("{}", v as i64)
+    } else {
+        let s = format!("{:.3}", v);
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+fn truncate_label(label: &str) -> String {
+    if label.chars().count() > 24 {
+        format!("{}…", label.chars().take(23).collect::<String>())
+    } else {
+        label.to_string()
+    }
+}
+
+fn write_value_axis(svg: &mut String, area: Rect, ticks: &[f64], title: &str) {
+    let (low, high) = (ticks[0], ticks[ticks.len() - 1]);
+    for tick in ticks {
+        let y = area.y + area.h - (tick - low) / (high - low) * area.h;
+        write!(
+            svg,
+            "<line x1='{:.1}' x2='{:.1}' y1='{:.1}' y2='{:.1}' stroke='{}'/>\
+            <text x='{:.1}' y='{:.1}' text-anchor='end' dominant-baseline='middle'>{}</text>",
+            area.x,
+            area.x + area.w,
+            y,
+            y,
+            CHART_GRID_COLOR,
+            area.x - 8.,
+            y,
+            format_number(*tick)
+        )
+        .unwrap();
+    }
+    write!(
+        svg,
+        "<text transform='translate(20 {:.1}) rotate(-90)' text-anchor='middle' font-weight='bold'>{}</text>",
+        area.y + area.h / 2.,
+        escape_html(title)
+    )
+    .unwrap();
+}
+
+fn write_category_axis(svg: &mut String, area: Rect, categories: &[String], rotate: bool, title: &str) {
+    let band = area.w / categories.len().max(1) as f64;
+    for (c, category) in categories.iter().enumerate() {
+        let x = area.x + band * (c as f64 + 0.5);
+        let y = area.y + area.h + 20.;
+        let label = escape_html(&truncate_label(category));
+        if rotate {
+            write!(
+                svg,
+                "<text transform='translate({:.1} {:.1}) rotate(-35)' text-anchor='end'>{}<title>{}</title></text>",
+                x,
+                y,
+                label,
+                escape_html(category)
+            )
+        } else {
+            write!(svg, "<text x='{:.1}' y='{:.1}' text-anchor='middle'>{}</text>", x, y, label)
+        }
+        .unwrap();
+    }
+    write_axis_title(svg, area, title, if rotate { 120. } else { 45. });
+}
+
+fn write_axis_title(svg: &mut String, area: Rect, title: &str, offset: f64) {
+    write!(
+        svg,
+        "<text x='{:.1}' y='{:.1}' text-anchor='middle' font-weight='bold'>{}</text>",
+        area.x + area.w / 2.,
+        area.y + area.h + offset,
+        escape_html(title)
+    )
+    .unwrap();
+}
+
+fn write_legend<'a>(svg: &mut String, names: impl Iterator<Item = &'a str>) {
+    let mut x = 80.;
+    for (i, name) in names.enumerate() {
+        let name = truncate_label(name);
+        write!(
+            svg,
+            "<rect x='{:.1}' y='12' width='14' height='14' rx='3' fill='{}'/>\
+            <text x='{:.1}' y='24'>{}</text>",
+            x,
+            CHART_PALETTE[i % CHART_PALETTE.len()],
+            x + 20.,
+            escape_html(&name)
+        )
+        .unwrap();
+        x += 40. + 7. * name.chars().count() as f64;
+    }
+}
+
+fn min_max(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)))
+}
+
+/// Adds a row with the path and the value of every node to `rows`.
+fn tree_rows(nodes: &[TreeNode], parent: &str, rows: &mut Vec<Vec<String>>) {
+    for node in nodes {
+        let path = if parent.is_empty() {
+            node.label.clone()
+        } else {
+            format!("{} / {}", parent, node.label)
+        };
+        rows.push(vec![path.clone(), format_number(node.value)]);
+        tree_rows(&node.children, &path, rows);
+    }
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Gaussian kernel density estimate of `values` at `samples` points between
+/// their minimum and maximum, with Silverman's rule of thumb as bandwidth.
+fn kernel_density(values: &[f64], samples: usize) -> Vec<(f64, f64)> {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let sd = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let (min, max) = values.iter().fold((f64::MAX, f64::MIN), |(a, b), v| (a.min(*v), b.max(*v)));
+    if sd == 0. || min == max {
+        return vec![(min, 1.), (max, 1.)];
+    }
+
+    let bandwidth = 1.06 * sd * n.powf(-0.2);
+    (0..samples)
+        .map(|i| {
+            let at = min + (max - min) * i as f64 / (samples - 1) as f64;
+            let density = values
+                .iter()
+                .map(|v| (-0.5 * ((at - v) / bandwidth).powi(2)).exp())
+                .sum::<f64>();
+            (at, density)
+        })
+        .collect()
+}
+
+/// Draws the nodes in `rect` as a squarified treemap, nesting their children
+/// below a label. Top level nodes pick a color that their children inherit.
+fn write_treemap(svg: &mut String, nodes: &[TreeNode], rect: Rect, color: Option<&str>) {
+    let mut nodes = nodes.iter().filter(|n| n.value > 0.).collect::<Vec<_>>();
+    nodes.sort_by(|a, b| b.value.total_cmp(&a.value));
+    let values = nodes.iter().map(|n| n.value).collect::<Vec<_>>();
+
+    for (i, (node, r)) in nodes.iter().zip(squarify(&values, rect)).enumerate() {
+        let color = color.unwrap_or(CHART_PALETTE[i % CHART_PALETTE.len()]);
+        write!(
+            svg,
+            "<rect x='{:.1}' y='{:.1}' width='{:.1}' height='{:.1}' fill='{}' fill-opacity='0.35' stroke='white' stroke-width='2'><title>{}: {}</title></rect>",
+            r.x,
+            r.y,
+            r.w,
+            r.h,
+            color,
+            escape_html(&node.label),
+            format_number(node.value)
+        )
+        .unwrap();
+        if r.w > 60. && r.h > 20. {
+            let max_chars = (r.w / 7.) as usize;
+            let label = node.label.chars().take(max_chars).collect::<String>();
+            write!(
+                svg,
+                "<text x='{:.1}' y='{:.1}'>{}</text>",
+                r.x + 5.,
+                r.y + 15.,
+                escape_html(&label)
+            )
+            .unwrap();
+        }
+        if !node.children.is_empty() && r.w > 20. && r.h > 40. {
+            let inner = Rect {
+                x: r.x + 3.,
+                y: r.y + 20.,
+                w: r.w - 6.,
+                h: r.h - 23.,
+            };
+            write_treemap(svg, &node.children, inner, Some(color));
+        }
+    }
+}
+
+/// Splits `rect` in rectangles with areas proportional to `values` (sorted
+/// in descending order), keeping them as close to squares as possible.
+fn squarify(values: &[f64], rect: Rect) -> Vec<Rect> {
+    let total: f64 = values.iter().sum();
+    if total <= 0. {
+        return vec![Rect { w: 0., h: 0., ..rect }; values.len()];
+    }
+    let areas = values
+        .iter()
+        .map(|v| v * rect.w * rect.h / total)
+        .collect::<Vec<_>>();
+
+    // Ratio between the longest and shortest side of the worst rectangle
+    // in a row of `areas` laid along `side`.
+    let worst = |row: &[f64], side: f64| {
+        let sum: f64 = row.iter().sum();
+        let max = row.iter().cloned().fold(f64::MIN, f64::max);
+        let min = row.iter().cloned().fold(f64::MAX, f64::min);
+        (side * side * max / (sum * sum)).max(sum * sum / (side * side * min))
+    };
+
+    let mut rects = Vec::new();
+    let mut rest = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = rest.w.min(rest.h);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..end + 1], side) <= worst(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row_area: f64 = areas[start..end].iter().sum();
+        if rest.w >= rest.h {
+            let w = row_area / rest.h;
+            let mut y = rest.y;
+            for area in &areas[start..end] {
+                let h = area / w;
+                rects.push(Rect { x: rest.x, y, w, h });
+                y += h;
+            }
+            rest = Rect {
+                x: rest.x + w,
+                w: rest.w - w,
+                ..rest
+            };
+        } else {
+            let h = row_area / rest.w;
+            let mut x = rest.x;
+            for area in &areas[start..end] {
+                let w = area / h;
+                rects.push(Rect { x, y: rest.y, w, h });
+                x += w;
+            }
+            rest = Rect {
+                y: rest.y + h,
+                h: rest.h - h,
+                ..rest
+            };
+        }
+        start = end;
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bar_chart_counts_rows() {
+        let spec: ChartSpec = toml::from_str("kind = 'bar'\ndata = 'tests/fixtures/chart.csv'\nx = 'feature'").unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Bar { categories, series, stacked } => {
+                assert_eq!(categories, ["D010", "D020", "D030"]);
+                assert_eq!(series.len(), 1);
+                assert_eq!(series[0].name, "Count");
+                assert_eq!(series[0].values, [2., 2., 1.]);
+                assert!(!stacked);
+            }
+            data => panic!("Expected a bar chart, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn bar_chart_sorts_numeric_categories() {
+        let spec: ChartSpec = toml::from_str("kind = 'bar'\ndata = 'tests/fixtures/chart.csv'\nx = 'year'").unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Bar { categories, series, .. } => {
+                assert_eq!(categories, ["2019", "2020", "2021"]);
+                assert_eq!(series[0].values, [1., 2., 2.]);
+            }
+            data => panic!("Expected a bar chart, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn stacked_bar_chart_normalizes() {
+        let spec: ChartSpec = toml::from_str(
+            "kind = 'stacked-bar'\ndata = 'tests/fixtures/chart.csv'\nx = 'feature'\ncolor = 'answer'\nnormalize = true",
+        )
+        .unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Bar { series, stacked, .. } => {
+                assert!(stacked);
+                assert_eq!(series[0].name, "Yes");
+                assert_eq!(series[0].values, [0.5, 1., 0.]);
+                assert_eq!(series[1].name, "No");
+                assert_eq!(series[1].values, [0.5, 0., 1.]);
+            }
+            data => panic!("Expected a bar chart, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn violin_chart_skips_nan() {
+        let spec: ChartSpec =
+            toml::from_str("kind = 'violin'\ndata = 'tests/fixtures/chart.csv'\nx = 'feature'\ny = 'score'").unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Violin { groups } => {
+                assert_eq!(groups[0], (String::from("D010"), vec![1.5, 2.]));
+                assert_eq!(groups[1], (String::from("D020"), vec![3.]));
+                assert_eq!(groups[2], (String::from("D030"), vec![4.25]));
+            }
+            data => panic!("Expected a violin chart, got {:?}", data),
+        }
+        assert_eq!(median(&[1.5, 2.]), 1.75);
+    }
+
+    #[test]
+    fn scatter_chart_skips_nan() {
+        let spec: ChartSpec =
+            toml::from_str("kind = 'scatter'\ndata = 'tests/fixtures/chart.csv'\nx = 'year'\ny = 'score'").unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Scatter { series } => {
+                assert_eq!(series.len(), 1);
+                assert_eq!(series[0].1, [(2019., 1.5), (2020., 2.), (2020., 3.), (2021., 4.25)]);
+            }
+            data => panic!("Expected a scatter chart, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn treemap_chart_nests_path() {
+        let spec: ChartSpec =
+            toml::from_str("kind = 'treemap'\ndata = 'tests/fixtures/chart.csv'\npath = ['section', 'division']").unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Treemap { roots } => {
+                let labels = roots.iter().map(|r| (r.label.as_str(), r.value)).collect::<Vec<_>>();
+                assert_eq!(labels, [("A", 2.), ("B", 2.), ("C", 1.)]);
+                let a = roots[0].children.iter().map(|c| c.label.as_str()).collect::<Vec<_>>();
+                assert_eq!(a, ["A01", "A02"]);
+                assert_eq!(roots[1].children.len(), 1);
+                assert_eq!(roots[1].children[0].value, 2.);
+            }
+            data => panic!("Expected a treemap, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn negative_bars_go_down_from_zero() {
+        let chart = Chart {
+            data: ChartData::Bar {
+                categories: vec![String::from("Gain"), String::from("Loss")],
+                series: vec![BarSeries {
+                    name: String::from("Change"),
+                    values: vec![3., -2.],
+                }],
+                stacked: false,
+            },
+            x_title: String::new(),
+            y_title: String::new(),
+        };
+        let svg = chart.to_svg();
+        assert!(!svg.contains("height='-"));
+
+        // Both bars start at the zero line: the gain ends there, and the loss
+        // starts there.
+        let rects = svg
+            .split("<rect ")
+            .skip(1)
+            .map(|r| {
+                let attribute = |name: &str| -> f64 {
+                    let (_, value) = r.split_once(&format!("{}='", name)).unwrap();
+                    value.split_once('\'').unwrap().0.parse().unwrap()
+                };
+                (attribute("y"), attribute("height"))
+            })
+            .filter(|(_, h)| *h > 0.)
+            .collect::<Vec<_>>();
+        let (gain, loss) = (rects[rects.len() - 2], rects[rects.len() - 1]);
+        assert!((gain.0 + gain.1 - loss.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn study_chart_matches_plotly_export() {
+        let spec: ChartSpec =
+            toml::from_str("kind = 'bar'\ndata = 'sms_figures/table.html'\nx = 'D060'").unwrap();
+        match Chart::from_spec(&spec).unwrap().data {
+            ChartData::Bar { categories, series, .. } => {
+                let years = (2011..=2022).map(|y| y.to_string()).collect::<Vec<_>>();
+                assert_eq!(categories, years);
+                assert_eq!(series[0].values, [3., 6., 3., 8., 6., 11., 11., 9., 20., 38., 29., 5.]);
+            }
+            data => panic!("Expected a bar chart, got {:?}", data),
+        }
+    }
+}
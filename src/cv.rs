@@ -0,0 +1,614 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+
+use chrono::{Duration, NaiveDate};
+
+use html_builder::*;
+
+use serde::Deserialize;
+
+use super::images::{Backgrounds, ResponsiveImage};
+use super::listing::post_type;
+use super::pdf::{PdfWriter, Span, Style};
+use super::site::SiteConfig;
+use super::tags::Taxonomy;
+use super::{escape_html, Page, Publication, CSS};
+
+/// Struct that represents a CV in the JSON Resume schema
+/// (https://jsonresume.org/schema), extended with `talks` and `teaching`.
+/// Its `publications` are left out, since the CV lists the gobbets instead.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Resume {
+    pub(crate) basics: Basics,
+    #[serde(default)]
+    education: Vec<Education>,
+    #[serde(default)]
+    work: Vec<Work>,
+    #[serde(default)]
+    awards: Vec<Award>,
+    #[serde(default)]
+    talks: Vec<Talk>,
+    #[serde(default)]
+    teaching: Vec<Teaching>,
+    #[serde(default)]
+    skills: Vec<Skill>,
+    meta: Option<Meta>,
+}
+
+/// Struct that represents the metadata of the CV, whose `last_modified` is
+/// the date of the PDF.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    last_modified: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Basics {
+    pub(crate) name: String,
+    pub(crate) label: Option<String>,
+    pub(crate) email: Option<String>,
+    pub(crate) url: Option<String>,
+    pub(crate) summary: Option<String>,
+    location: Option<Location>,
+    #[serde(default)]
+    pub(crate) profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    city: Option<String>,
+    region: Option<String>,
+    country_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Profile {
+    pub(crate) network: String,
+    pub(crate) username: Option<String>,
+    pub(crate) url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Education {
+    institution: String,
+    url: Option<String>,
+    area: Option<String>,
+    study_type: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    score: Option<String>,
+    summary: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Work {
+    name: String,
+    position: Option<String>,
+    url: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    summary: Option<String>,
+    #[serde(default)]
+    highlights: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Award {
+    title: String,
+    date: Option<String>,
+    awarder: Option<String>,
+    summary: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Talk {
+    title: String,
+    event: Option<String>,
+    date: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Teaching {
+    course: String,
+    institution: Option<String>,
+    role: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Skill {
+    name: String,
+    level: Option<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+}
+
+impl Resume {
+    pub(crate) fn from_file(cv_path: &str) -> Result<Self, &'static str> {
+        println!("Reading CV file {:?}", cv_path);
+        let cv = match fs::read_to_string(cv_path) {
+            Ok(c) => c,
+            _ => return Err("Couldn't read CV file."),
+        };
+        match serde_json::from_str(&cv) {
+            Ok(r) => Ok(r),
+            Err(e) => {
+                println!("{}", e);
+                Err("Couldn't parse CV file.")
+            }
+        }
+    }
+
+    /// Date the CV was last modified, as its `meta.lastModified` tells, or
+    /// else when the site configuration was, so that building the same
+    /// sources again gives the same PDF.
+    fn last_modified(&self, config: &SiteConfig) -> NaiveDate {
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let meta = self
+            .meta
+            .as_ref()
+            .and_then(|m| m.last_modified.as_deref())
+            .and_then(|date| NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok());
+        let config = config
+            .modified
+            .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| epoch + Duration::days(d.as_secs() as i64 / 86400));
+        meta.or(config).unwrap_or(epoch)
+    }
+
+    /// Sections of entries, in the order the CV shows them, with
+    /// "Publications" after the first two.
+    fn sections(&self) -> Vec<(&'static str, Vec<Entry>)> {
+        let education = self
+            .education
+            .iter()
+            .map(|e| Entry {
+                title: e.institution.clone(),
+                url: e.url.clone(),
+                details: [
+                    Some(
+                        [e.study_type.as_deref(), e.area.as_deref()]
+                            .iter()
+                            .flatten()
+                            .copied()
+                            .collect::<Vec<_>>()
+                            .join(" in "),
+                    )
+                    .filter(|degree| !degree.is_empty()),
+                    e.score.clone(),
+                ]
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+                dates: date_range(&e.start_date, &e.end_date),
+                summary: e.summary.clone(),
+                highlights: Vec::new(),
+            })
+            .collect();
+        let work = self
+            .work
+            .iter()
+            .map(|w| Entry {
+                title: w.name.clone(),
+                url: w.url.clone(),
+                details: w.position.clone().unwrap_or_default(),
+                dates: date_range(&w.start_date, &w.end_date),
+                summary: w.summary.clone(),
+                highlights: w.highlights.clone(),
+            })
+            .collect();
+        let awards = self
+            .awards
+            .iter()
+            .map(|a| Entry {
+                title: a.title.clone(),
+                url: None,
+                details: a.awarder.clone().unwrap_or_default(),
+                dates: a.date.clone().unwrap_or_default(),
+                summary: a.summary.clone(),
+                highlights: Vec::new(),
+            })
+            .collect();
+        let talks = self
+            .talks
+            .iter()
+            .map(|t| Entry {
+                title: t.title.clone(),
+                url: t.url.clone(),
+                details: [t.event.as_deref(), t.location.as_deref()]
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                dates: t.date.clone().unwrap_or_default(),
+                summary: None,
+                highlights: Vec::new(),
+            })
+            .collect();
+        let teaching = self
+            .teaching
+            .iter()
+            .map(|t| Entry {
+                title: t.course.clone(),
+                url: None,
+                details: [t.role.as_deref(), t.institution.as_deref()]
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                dates: date_range(&t.start_date, &t.end_date),
+                summary: None,
+                highlights: Vec::new(),
+            })
+            .collect();
+
+        vec![
+            ("Education", education),
+            ("Experience", work),
+            ("Awards", awards),
+            ("Talks", talks),
+            ("Teaching", teaching),
+        ]
+    }
+
+    /// Contacts of the basics, as their text and the link they open.
+    fn contacts(&self) -> Vec<(String, Option<String>)> {
+        let basics = &self.basics;
+        let mut contacts = Vec::new();
+        if let Some(email) = &basics.email {
+            contacts.push((email.clone(), Some(format!("mailto:{}", email))));
+        }
+        if let Some(url) = &basics.url {
+            contacts.push((url.clone(), Some(url.clone())));
+        }
+        if let Some(location) = &basics.location {
+            let place = [&location.city, &location.region, &location.country_code]
+                .iter()
+                .filter_map(|p| p.as_deref())
+                .collect::<Vec<_>>()
+                .join(", ");
+            contacts.push((place, None));
+        }
+        for profile in &basics.profiles {
+            let text = match &profile.username {
+                Some(username) => format!("{}: {}", profile.network, username),
+                None => profile.network.clone(),
+            };
+            contacts.push((text, Some(profile.url.clone())));
+        }
+        contacts
+    }
+}
+
+/// Struct that represents an entry of a section of the CV, as both the page
+/// and the PDF show it.
+struct Entry {
+    title: String,
+    url: Option<String>,
+    /// What the entry was, such as the degree or the position.
+    details: String,
+    dates: String,
+    summary: Option<String>,
+    highlights: Vec<String>,
+}
+
+/// Dates of something that went from `start` to `end`, or is still going on
+/// when it has no `end`, and nothing when it has neither. Dates are shown as
+/// the file has them.
+fn date_range(start: &Option<String>, end: &Option<String>) -> String {
+    match (start, end) {
+        (Some(start), Some(end)) => format!("{} – {}", start, end),
+        (Some(start), None) => format!("{} – Present", start),
+        (None, Some(end)) => end.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Papers grouped by their type, in the order of the types in the config,
+/// and those of no type last, as "Other".
+fn publication_groups<'a>(
+    papers: &'a [Publication],
+    taxonomy: &'a Taxonomy,
+    config: &'a SiteConfig,
+) -> Vec<(&'a str, Vec<&'a Publication>)> {
+    let types = &config.publications.types;
+    types
+        .iter()
+        .map(|t| Some(t.as_str()))
+        .chain(std::iter::once(None))
+        .map(|group| {
+            (
+                group.map(|t| taxonomy.name(t)).unwrap_or("Other"),
+                papers
+                    .iter()
+                    .filter(|post| post_type(types, post) == group)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .filter(|(_, group_papers)| !group_papers.is_empty())
+        .collect()
+}
+
+/// Writes `text` as a link to `url`, or as is without one.
+fn write_link(w: &mut dyn Write, text: &str, url: &Option<String>) {
+    match url {
+        Some(url) => write!(w, "<a href='{}'>{}</a>", escape_html(url), escape_html(text)),
+        None => write!(w, "{}", escape_html(text)),
+    }
+    .unwrap();
+}
+
+fn write_entry(w: &mut dyn Write, entry: &Entry) {
+    write!(w, "<div class='cv-entry'><div class='cv-entry-header'><span><b>").unwrap();
+    write_link(w, &entry.title, &entry.url);
+    write!(w, "</b>").unwrap();
+    if !entry.details.is_empty() {
+        write!(w, ", {}", escape_html(&entry.details)).unwrap();
+    }
+    write!(w, "</span><span class='cv-date'>{}</span></div>", escape_html(&entry.dates)).unwrap();
+    if let Some(summary) = &entry.summary {
+        write!(w, "<p class='no_margin'>{}</p>", escape_html(summary)).unwrap();
+    }
+    if !entry.highlights.is_empty() {
+        write!(w, "<ul class='cv-highlights'>").unwrap();
+        for highlight in &entry.highlights {
+            write!(w, "<li>{}</li>", escape_html(highlight)).unwrap();
+        }
+        write!(w, "</ul>").unwrap();
+    }
+    write!(w, "</div>").unwrap();
+}
+
+/// Builds the CV page from `resume`, listing `papers` as its publications,
+/// grouped by the type of publication, and its PDF.
+pub(crate) fn build_cv(
+    resume: &Resume,
+    papers: &[Publication],
+    taxonomy: &Taxonomy,
+    config: &SiteConfig,
+    backgrounds: &Backgrounds,
+    profile_pic: &ResponsiveImage,
+) -> Result<(), Box<dyn Error>> {
+    write_cv_pdf(resume, papers, taxonomy, config)?;
+
+    let mut page = Page::new("CV", CSS::CV, config, backgrounds);
+    page.add_site_top_bar(profile_pic, Some("CV"));
+
+    {
+        let mut container = page
+            .buf
+            .div()
+            .attr("class='blank-container page-ultra-wide-treatment cv'");
+
+        let basics = &resume.basics;
+        let mut header = container.div().attr("class='cv-header'");
+        write!(header.h1().attr("class='no_margin'"), "{}", escape_html(&basics.name))?;
+        if let Some(label) = &basics.label {
+            write!(header.h4(), "{}", escape_html(label))?;
+        }
+        let mut contacts = header.ul().attr("class='cv-contacts'");
+        for (text, url) in resume.contacts() {
+            write_link(&mut contacts.li(), &text, &url);
+        }
+        write!(
+            header
+                .a()
+                .attr(format!("class='btn btn-primary cv-print' href='{}' download", FILE_CV_PDF).as_str()),
+            "<i class='fa-solid fa-file-pdf'></i> Download PDF"
+        )?;
+        write!(
+            header
+                .button()
+                .attr("class='btn btn-link cv-print' onclick='window.print()'"),
+            "<i class='fa-solid fa-print'></i> Print"
+        )?;
+
+        if let Some(summary) = &basics.summary {
+            write!(container.p().attr("class='cv-summary'"), "{}", escape_html(summary))?;
+        }
+
+        for (index, (title, entries)) in resume.sections().iter().enumerate() {
+            if !entries.is_empty() {
+                let mut section = container.section().attr("class='cv-section'");
+                write!(section.h2(), "{}", title)?;
+                for entry in entries {
+                    write_entry(&mut section, entry);
+                }
+            }
+
+            if index == 1 && !papers.is_empty() {
+                let mut section = container.section().attr("class='cv-section'");
+                write!(section.h2(), "Publications")?;
+                for (group, group_papers) in publication_groups(papers, taxonomy, config) {
+                    write!(section.h3(), "{}", escape_html(group))?;
+                    let mut list = section.ol().attr("class='cv-publications'");
+                    for post in group_papers {
+                        let mut item = list.li();
+                        write_link(
+                            &mut item,
+                            &post.title,
+                            &Some(config.absolute_url(&post.page_path())),
+                        );
+                        write!(item.span().attr("class='cv-date'"), " {}", post.date.format("%Y"))?;
+                    }
+                }
+            }
+        }
+
+        if !resume.skills.is_empty() {
+            let mut section = container.section().attr("class='cv-section'");
+            write!(section.h2(), "Skills")?;
+            let mut list = section.dl().attr("class='cv-skills'");
+            for skill in &resume.skills {
+                write!(
+                    list.dt(),
+                    "{}{}",
+                    escape_html(&skill.name),
+                    match &skill.level {
+                        Some(level) => format!(" <small>({})</small>", escape_html(level)),
+                        None => String::new(),
+                    }
+                )?;
+                write!(list.dd(), "{}", escape_html(&skill.keywords.join(", ")))?;
+            }
+        }
+    }
+
+    page.add_footer();
+    page.publish("cv.html");
+
+    Ok(())
+}
+
+pub(crate) const FILE_CV_PDF: &str = "cv.pdf";
+
+/// Writes the CV to its PDF, with the same sections as its page. Links to the
+/// publications are only kept when the address of the site is known.
+fn write_cv_pdf(
+    resume: &Resume,
+    papers: &[Publication],
+    taxonomy: &Taxonomy,
+    config: &SiteConfig,
+) -> Result<(), &'static str> {
+    let basics = &resume.basics;
+    let keywords = resume
+        .skills
+        .iter()
+        .flat_map(|s| std::iter::once(&s.name).chain(&s.keywords))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut pdf = PdfWriter::new(
+        &format!("{} | CV", basics.name),
+        &basics.name,
+        basics.label.as_deref().unwrap_or("Curriculum vitae"),
+        &keywords,
+        resume.last_modified(config),
+    )?;
+
+    pdf.heading(1, &[Span::new(&basics.name, Style::Bold)]);
+    if let Some(label) = &basics.label {
+        pdf.paragraph(&[Span::new(label, Style::Italic)]);
+    }
+    let mut contacts = Vec::new();
+    for (text, url) in resume.contacts() {
+        if !contacts.is_empty() {
+            contacts.push(Span::new(" · ", Style::Regular));
+        }
+        contacts.push(match url {
+            Some(url) => Span::link(&text, &url),
+            None => Span::new(&text, Style::Regular),
+        });
+    }
+    pdf.paragraph(&contacts);
+    if let Some(summary) = &basics.summary {
+        pdf.paragraph(&[Span::new(summary, Style::Regular)]);
+    }
+
+    for (index, (title, entries)) in resume.sections().iter().enumerate() {
+        if !entries.is_empty() {
+            pdf.heading(2, &[Span::new(title, Style::Bold)]);
+            for entry in entries {
+                let mut left = vec![Span {
+                    link: entry.url.clone(),
+                    ..Span::new(&entry.title, Style::Bold)
+                }];
+                if !entry.details.is_empty() {
+                    left.push(Span::new(&format!(", {}", entry.details), Style::Regular));
+                }
+                pdf.entry(&left, &entry.dates);
+                if let Some(summary) = &entry.summary {
+                    pdf.paragraph(&[Span::new(summary, Style::Regular)]);
+                }
+                for highlight in &entry.highlights {
+                    pdf.item("•", &[Span::new(highlight, Style::Regular)]);
+                }
+                pdf.space(2.);
+            }
+        }
+
+        if index == 1 && !papers.is_empty() {
+            pdf.heading(2, &[Span::new("Publications", Style::Bold)]);
+            for (group, group_papers) in publication_groups(papers, taxonomy, config) {
+                pdf.heading(3, &[Span::new(group, Style::Bold)]);
+                for (number, post) in group_papers.iter().enumerate() {
+                    let title = match config.url {
+                        Some(_) => Span::link(&post.title, &config.absolute_url(&post.page_path())),
+                        None => Span::new(&post.title, Style::Regular),
+                    };
+                    pdf.item(
+                        &format!("{}.", number + 1),
+                        &[title, Span::new(&format!(", {}", post.date.format("%Y")), Style::Regular)],
+                    );
+                }
+            }
+        }
+    }
+
+    if !resume.skills.is_empty() {
+        pdf.heading(2, &[Span::new("Skills", Style::Bold)]);
+        for skill in &resume.skills {
+            let mut name = skill.name.clone();
+            if let Some(level) = &skill.level {
+                name = format!("{} ({})", name, level);
+            }
+            pdf.paragraph(&[
+                Span::new(&format!("{}: ", name), Style::Bold),
+                Span::new(&skill.keywords.join(", "), Style::Regular),
+            ]);
+        }
+    }
+
+    pdf.save(FILE_CV_PDF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::FILE_CV;
+
+    #[test]
+    fn resume_from_file() {
+        let resume = Resume::from_file(FILE_CV).unwrap();
+        assert_eq!(resume.basics.name, "Cláudio Gomes");
+        assert_eq!(resume.education.len(), 1);
+        assert_eq!(resume.sections()[0].0, "Education");
+    }
+
+    #[test]
+    fn date_range_leaves_out_missing_dates() {
+        let date = |d: &str| Some(d.to_string());
+        assert_eq!(date_range(&date("2019"), &date("2021")), "2019 – 2021");
+        assert_eq!(date_range(&date("2021"), &None), "2021 – Present");
+        assert_eq!(date_range(&None, &date("2021")), "2021");
+        assert_eq!(date_range(&None, &None), "");
+    }
+
+    #[test]
+    fn pdf_date_from_meta_or_config() {
+        let mut resume: Resume = serde_json::from_str(
+            r#"{"basics": {"name": "Name"}, "meta": {"lastModified": "2022-05-17T10:30:00"}}"#,
+        )
+        .unwrap();
+        let mut config = SiteConfig::default();
+        assert_eq!(resume.last_modified(&config), NaiveDate::from_ymd_opt(2022, 5, 17).unwrap());
+
+        resume.meta = None;
+        assert_eq!(resume.last_modified(&config), NaiveDate::from_ymd_opt(1970, 1, 1).unwrap());
+        config.modified = Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(2 * 86400 + 3600));
+        assert_eq!(resume.last_modified(&config), NaiveDate::from_ymd_opt(1970, 1, 3).unwrap());
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+
+use chrono::NaiveDate;
+
+use exif::{Exif, In, Tag, Value};
+
+use html_builder::*;
+
+use rayon::prelude::*;
+
+use serde::Deserialize;
+
+use super::images::{Backgrounds, ResponsiveImage};
+use super::site::SiteConfig;
+use super::{escape_html, titlefy_to_page_title, Page, CSS};
+
+/// Width, in CSS pixels, of a photo on its own page.
+const PHOTO_WIDTH: u32 = 960;
+
+/// Struct that represents a photo of the gallery, with what its EXIF data and
+/// its entry in the captions file tell about it. Its variants are encoded
+/// again from the pixels only, so they carry no EXIF data, GPS included.
+pub(crate) struct Photo {
+    file_name: String,
+    pub(crate) image: ResponsiveImage,
+    caption: PhotoCaption,
+    exif: PhotoExif,
+}
+
+/// Struct that represents the entry of a photo in the captions file, whose
+/// `date`, `camera` and `lens` take the place of the ones in its EXIF data,
+/// e.g. for scanned film.
+#[derive(Debug, Default, Clone, Deserialize)]
+struct PhotoCaption {
+    title: Option<String>,
+    caption: Option<String>,
+    date: Option<String>,
+    camera: Option<String>,
+    lens: Option<String>,
+    /// Whether to show where the photo was taken, which is hidden by default.
+    #[serde(default)]
+    show_location: bool,
+}
+
+/// Struct that represents the EXIF data of a photo that is worth showing.
+#[derive(Debug, Default)]
+struct PhotoExif {
+    date: Option<NaiveDate>,
+    camera: Option<String>,
+    lens: Option<String>,
+    exposure: Option<String>,
+    /// Latitude and longitude, in degrees.
+    location: Option<(f64, f64)>,
+}
+
+impl PhotoExif {
+    /// Reads the EXIF data of the image at `image_path`. Images without any
+    /// just have none to show.
+    fn from_file(image_path: &str) -> Self {
+        let exif = match File::open(image_path)
+            .ok()
+            .and_then(|f| exif::Reader::new().read_from_container(&mut BufReader::new(f)).ok())
+        {
+            Some(e) => e,
+            None => return Self::default(),
+        };
+
+        let date = ascii(&exif, Tag::DateTimeOriginal)
+            .and_then(|d| NaiveDate::parse_from_str(d.get(..10)?, "%Y:%m:%d").ok());
+
+        let camera = match (ascii(&exif, Tag::Make), ascii(&exif, Tag::Model)) {
+            // Models often repeat the make already.
+            (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.or(model),
+        };
+
+        let exposure = [
+            rational(&exif, Tag::ExposureTime).map(|t| match t < 1. {
+                true => format!("1/{} s", (1. / t).round()),
+                false => format!("{} s", t),
+            }),
+            rational(&exif, Tag::FNumber).map(|f| format!("f/{}", f)),
+            exif.get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+                .and_then(|f| f.value.get_uint(0))
+                .map(|iso| format!("ISO {}", iso)),
+            rational(&exif, Tag::FocalLength).map(|l| format!("{} mm", l)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        let location = match (
+            coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+            coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+        ) {
+            (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+            _ => None,
+        };
+
+        Self {
+            date,
+            camera,
+            lens: ascii(&exif, Tag::LensModel),
+            exposure: match exposure.is_empty() {
+                true => None,
+                false => Some(exposure.join(", ")),
+            },
+            location,
+        }
+    }
+}
+
+/// Text of the ASCII field `tag`.
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim().to_string())
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    }
+}
+
+/// Value of the rational field `tag`.
+fn rational(exif: &Exif, tag: Tag) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) => values.first().map(|v| v.to_f64()),
+        _ => None,
+    }
+}
+
+/// Coordinate in degrees of the degrees, minutes and seconds in `tag`, which
+/// is negative when `ref_tag` is `negative_ref`.
+fn coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) if values.len() == 3 => {
+            values[0].to_f64() + values[1].to_f64() / 60. + values[2].to_f64() / 3600.
+        }
+        _ => return None,
+    };
+    match ascii(exif, ref_tag)?.as_str() == negative_ref {
+        true => Some(-degrees),
+        false => Some(degrees),
+    }
+}
+
+impl Photo {
+    /// Reads the photos of the gallery, the most recent first, and makes
+    /// their variants in `to_dest_dir`.
+    pub(crate) fn from_config(config: &SiteConfig, to_dest_dir: &str) -> Result<Vec<Self>, &'static str> {
+        let gallery = &config.gallery;
+
+        let mut captions: HashMap<String, PhotoCaption> = match fs::read_to_string(&gallery.captions) {
+            Ok(c) => match toml::from_str(&c) {
+                Ok(c) => c,
+                Err(e) => {
+                    println!("{}", e);
+                    return Err("Couldn't parse photo captions.");
+                }
+            },
+            _ => HashMap::new(),
+        };
+
+        let paths = match fs::read_dir(&gallery.source) {
+            Ok(p) => p,
+            _ => return Err("Couldn't open folder."),
+        };
+        let file_names = paths
+            .filter_map(|p| p.ok().map(|p| p.file_name().to_string_lossy().into_owned()))
+            .filter(|name| {
+                image::ImageFormat::from_path(name).is_ok() && !gallery.exclude.contains(name)
+            })
+            .collect::<Vec<_>>();
+
+        let images = file_names
+            .par_iter()
+            .map(|name| ResponsiveImage::from_file(&format!("{}/{}", gallery.source, name), to_dest_dir))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut photos = file_names
+            .into_iter()
+            .zip(images)
+            .map(|(file_name, image)| Self {
+                exif: PhotoExif::from_file(&format!("{}/{}", gallery.source, file_name)),
+                caption: captions.remove(&file_name).unwrap_or_default(),
+                file_name,
+                image,
+            })
+            .collect::<Vec<_>>();
+        for file_name in captions.keys() {
+            println!("Couldn't find photo {:?} to caption.", file_name);
+        }
+
+        photos.sort_by(|a, b| b.date().cmp(&a.date()).then(a.file_name.cmp(&b.file_name)));
+        Ok(photos)
+    }
+
+    fn title(&self) -> &str {
+        self.caption.title.as_deref().unwrap_or(&self.image.name)
+    }
+
+    fn date(&self) -> Option<NaiveDate> {
+        match &self.caption.date {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y/%m/%d").ok(),
+            None => self.exif.date,
+        }
+    }
+
+    fn page_path(&self) -> String {
+        format!("gallery-{}.html", titlefy_to_page_title(&self.image.name))
+    }
+
+    /// Details of the photo, as label and value pairs.
+    fn details(&self) -> Vec<(&'static str, String)> {
+        let mut details = Vec::new();
+        if let Some(date) = self.date() {
+            details.push(("Date", date.format("%-d %B %Y").to_string()));
+        }
+        if let Some(camera) = self.caption.camera.as_ref().or(self.exif.camera.as_ref()) {
+            details.push(("Camera", camera.clone()));
+        }
+        if let Some(lens) = self.caption.lens.as_ref().or(self.exif.lens.as_ref()) {
+            details.push(("Lens", lens.clone()));
+        }
+        if let Some(exposure) = &self.exif.exposure {
+            details.push(("Exposure", exposure.clone()));
+        }
+        if let (true, Some((latitude, longitude))) = (self.caption.show_location, self.exif.location) {
+            details.push((
+                "Location",
+                format!(
+                    "<a href='https://www.openstreetmap.org/?mlat={lat:.5}&amp;mlon={lon:.5}#map=13/{lat:.5}/{lon:.5}'>{lat:.5}, {lon:.5}</a>",
+                    lat = latitude,
+                    lon = longitude
+                ),
+            ));
+        }
+        details
+    }
+
+    /// One line summary of the photo, for captions and sharing previews.
+    fn summary(&self) -> String {
+        let mut summary = self.caption.caption.clone().unwrap_or_default();
+        let details = self
+            .details()
+            .into_iter()
+            .filter(|(label, _)| *label != "Location")
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        if !details.is_empty() {
+            if !summary.is_empty() {
+                summary.push(' ');
+            }
+            write!(summary, "({})", details.join(" · ")).unwrap();
+        }
+        summary
+    }
+}
+
+/// Builds the "Gallery" page, with a grid of every photo that opens them in a
+/// lightbox, and a page for each photo.
+pub(crate) fn build_gallery(
+    photos: &[Photo],
+    config: &SiteConfig,
+    backgrounds: &Backgrounds,
+    profile_pic: &ResponsiveImage,
+) -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new("Gallery", CSS::Science, config, backgrounds);
+    page.add_site_top_bar(profile_pic, None);
+
+    {
+        let mut container = page
+            .buf
+            .div()
+            .attr("class='blank-container page-ultra-wide-treatment'");
+        write!(container.h1(), "Gallery")?;
+        write!(
+            container.h4(),
+            "Photos I took, most of which are also on <a href='https://unsplash.com/@cfpgomes'>Unsplash</a>."
+        )?;
+
+        let mut grid = container.div().attr("class='gallery-grid'");
+        for photo in photos {
+            let mut item = grid.figure().attr("class='gallery-item'");
+            let mut link = item.a().attr(format!("href='{}'", photo.page_path()).as_str());
+            photo.image.write_picture(
+                &mut link,
+                photo.title(),
+                "(max-width: 600px) 100vw, (max-width: 1200px) 50vw, 33vw",
+                "img-responsive",
+            );
+            let mut caption = item.figcaption();
+            write!(caption.b(), "{}", escape_html(photo.title()))?;
+            write!(caption.span().attr("class='gallery-summary'"), "{}", escape_html(&photo.summary()))?;
+        }
+    }
+
+    // Without scripts, photos simply open their own page.
+    write!(
+        page.buf,
+        "<dialog class='gallery-lightbox' id='gallery-lightbox-id' aria-label='Photo'>\
+        <button class='btn btn-clear gallery-lightbox-close' aria-label='Close'></button>\
+        <div class='gallery-lightbox-picture' id='gallery-lightbox-picture-id'></div>\
+        <p class='gallery-lightbox-caption' id='gallery-lightbox-caption-id'></p>\
+        <div class='gallery-lightbox-controls'>\
+        <button class='btn btn-link' data-step='-1'><i class='fa-solid fa-chevron-left'></i> Previous</button>\
+        <a class='btn btn-link' id='gallery-lightbox-link-id'>Details</a>\
+        <button class='btn btn-link' data-step='1'>Next <i class='fa-solid fa-chevron-right'></i></button>\
+        </div></dialog>"
+    )?;
+    write!(
+        page.buf.script(),
+        "
+        var galleryItems = document.querySelectorAll('.gallery-item');
+        var galleryLightbox = document.getElementById('gallery-lightbox-id');
+        var galleryIndex = 0;
+
+        function showPhoto(index) {{
+            galleryIndex = (index + galleryItems.length) % galleryItems.length;
+            var item = galleryItems[galleryIndex];
+
+            // The picture of the grid, which the lightbox shows larger.
+            var picture = item.querySelector('picture').cloneNode(true);
+            picture.querySelectorAll('source, img').forEach(function(e) {{ e.sizes = '90vw'; }});
+            picture.querySelector('img').loading = 'eager';
+            document.getElementById('gallery-lightbox-picture-id').replaceChildren(picture);
+            document.getElementById('gallery-lightbox-caption-id').innerHTML = item.querySelector('figcaption').innerHTML;
+            document.getElementById('gallery-lightbox-link-id').href = item.querySelector('a').href;
+        }}
+
+        if (galleryLightbox.showModal) {{
+            galleryItems.forEach(function(item, index) {{
+                item.querySelector('a').addEventListener('click', function(event) {{
+                    event.preventDefault();
+                    showPhoto(index);
+                    galleryLightbox.showModal();
+                }});
+            }});
+            galleryLightbox.querySelectorAll('[data-step]').forEach(function(button) {{
+                button.addEventListener('click', function() {{
+                    showPhoto(galleryIndex + parseInt(button.dataset.step));
+                }});
+            }});
+            galleryLightbox.querySelector('.gallery-lightbox-close').addEventListener('click', function() {{
+                galleryLightbox.close();
+            }});
+            document.addEventListener('keydown', function(event) {{
+                if (galleryLightbox.open && event.key === 'ArrowLeft') {{
+                    showPhoto(galleryIndex - 1);
+                }} else if (galleryLightbox.open && event.key === 'ArrowRight') {{
+                    showPhoto(galleryIndex + 1);
+                }}
+            }});
+        }}
+        "
+    )?;
+
+    page.add_footer();
+    page.publish("gallery.html");
+
+    for (i, photo) in photos.iter().enumerate() {
+        build_photo_page(photo, photos, i, config, backgrounds, profile_pic)?;
+    }
+
+    Ok(())
+}
+
+fn build_photo_page(
+    photo: &Photo,
+    photos: &[Photo],
+    i: usize,
+    config: &SiteConfig,
+    backgrounds: &Backgrounds,
+    profile_pic: &ResponsiveImage,
+) -> Result<(), Box<dyn Error>> {
+    photo_page(photo, photos, i, config, backgrounds, profile_pic)?.publish(&photo.page_path());
+    Ok(())
+}
+
+/// Page of the `i`th photo of `photos`, with what sharing previews are made
+/// from, and links to the photos next to it.
+fn photo_page(
+    photo: &Photo,
+    photos: &[Photo],
+    i: usize,
+    config: &SiteConfig,
+    backgrounds: &Backgrounds,
+    profile_pic: &ResponsiveImage,
+) -> Result<Page, Box<dyn Error>> {
+    let page_url = config.absolute_url(&photo.page_path());
+    let image_url = config.absolute_url(photo.image.jpeg_path(PHOTO_WIDTH * 2));
+    let summary = photo.summary();
+    let meta = [
+        ("og:type", String::from("article")),
+        ("og:title", photo.title().to_string()),
+        ("og:description", summary.clone()),
+        ("og:url", page_url.clone()),
+        ("og:image", image_url),
+        ("og:image:alt", photo.title().to_string()),
+        ("twitter:card", String::from("summary_large_image")),
+    ];
+
+    let mut page = Page::new_with_meta(photo.title(), CSS::Science, config, backgrounds, &meta, None);
+    page.add_site_top_bar(profile_pic, None);
+
+    {
+        let mut container = page
+            .buf
+            .div()
+            .attr("class='blank-container page-ultra-wide-treatment gallery-photo'");
+        write!(container.h1(), "{}", escape_html(photo.title()))?;
+        photo.image.write_picture(
+            &mut container,
+            photo.title(),
+            &format!("(max-width: {0}px) 100vw, {0}px", PHOTO_WIDTH),
+            "img-responsive",
+        );
+        if let Some(caption) = &photo.caption.caption {
+            write!(container.p(), "{}", escape_html(caption))?;
+        }
+
+        let details = photo.details();
+        if !details.is_empty() {
+            let mut list = container.dl().attr("class='gallery-details'");
+            for (label, value) in details {
+                write!(list.dt(), "{}", label)?;
+                match label {
+                    // Already HTML, made from numbers only.
+                    "Location" => write!(list.dd(), "{}", value)?,
+                    _ => write!(list.dd(), "{}", escape_html(&value))?,
+                }
+            }
+        }
+
+        // Shared links have to be absolute, so sharing needs the address of
+        // the site.
+        if config.url.is_some() {
+            let mut share = container.div().attr("class='gallery-share'");
+            for (sharer, icon) in [("twitter", "fa-twitter"), ("facebook", "fa-facebook"), ("linkedin", "fa-linkedin-in")] {
+                share.button().attr(
+                    format!(
+                        "class='btn btn-action fa-brands {}' aria-label='Share on {}' data-sharer='{}' data-title='{}' data-url='{}'",
+                        icon,
+                        sharer,
+                        sharer,
+                        escape_html(photo.title()),
+                        escape_html(&page_url)
+                    )
+                    .as_str(),
+                );
+            }
+        }
+
+        let mut navigation = container.div().attr("class='gallery-navigation'");
+        let previous = &photos[(i + photos.len() - 1) % photos.len()];
+        let next = &photos[(i + 1) % photos.len()];
+        write!(
+            navigation.a().attr(format!("class='btn btn-link' href='{}'", previous.page_path()).as_str()),
+            "<i class='fa-solid fa-chevron-left'></i> {}",
+            escape_html(previous.title())
+        )?;
+        write!(
+            navigation.a().attr("class='btn btn-link' href='gallery.html'"),
+            "Gallery"
+        )?;
+        write!(
+            navigation.a().attr(format!("class='btn btn-link' href='{}'", next.page_path()).as_str()),
+            "{} <i class='fa-solid fa-chevron-right'></i>",
+            escape_html(next.title())
+        )?;
+    }
+
+    page.add_footer();
+
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn photo_page_escapes_caption_title() {
+        let folder = std::env::temp_dir().join("personal-website-gallery");
+        fs::create_dir_all(&folder).unwrap();
+        let source = folder.join("ribeira.png");
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(16, 8, image::Rgb([235, 233, 228])))
+            .save(&source)
+            .unwrap();
+        let image = ResponsiveImage::from_file(source.to_str().unwrap(), folder.to_str().unwrap()).unwrap();
+        let photo = Photo {
+            file_name: String::from("ribeira.png"),
+            image: image.clone(),
+            caption: PhotoCaption {
+                title: Some(String::from("Porto's <Ribeira> & the river")),
+                caption: Some(String::from("Taken at \"dusk\".")),
+                ..PhotoCaption::default()
+            },
+            exif: PhotoExif::default(),
+        };
+
+        let photos = [photo];
+        let page = photo_page(
+            &photos[0],
+            &photos,
+            0,
+            &SiteConfig::default(),
+            &Backgrounds::default(),
+            &image,
+        )
+        .unwrap();
+        let html = page.buf.finish();
+        let escaped = "Porto&#39;s &lt;Ribeira&gt; &amp; the river";
+        assert!(html.contains(&format!("Cláudio Gomes | {}", escaped)));
+        assert!(html.contains(&format!("<meta name='description' content='{}'>", escaped)));
+        assert!(html.contains(&format!("<meta property='og:title' content='{}'>", escaped)));
+        assert!(html.contains("Taken at &quot;dusk&quot;."));
+        assert!(!html.contains("<Ribeira>"));
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use comrak::adapters::SyntaxHighlighterAdapter;
+use comrak::{markdown_to_html_with_plugins, ComrakOptions, ComrakPlugins};
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{
+    css_for_theme_with_class_style, styled_line_to_highlighted_html, ClassStyle,
+    ClassedHTMLGenerator, IncludeBackground,
+};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use super::escape_html;
+use super::site::{HighlightConfig, HighlightStyle};
+
+/// Folder of the syntaxes that don't come with syntect, such as Q# and
+/// OpenQASM, as `.sublime-syntax` files.
+pub(crate) const FOLDER_SYNTAXES: &str = "syntaxes";
+pub(crate) const FILE_HIGHLIGHT_CSS: &str = "css/highlight.css";
+
+/// Classes of highlighted code are prefixed, so that they don't clash with
+/// those of Spectre.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Struct that represents how code blocks are highlighted when the site is
+/// built: with which syntaxes, for which languages, and in which themes.
+pub(crate) struct Highlighter {
+    syntaxes: SyntaxSet,
+    /// Languages that are highlighted, in lowercase.
+    languages: Vec<String>,
+    style: HighlightStyle,
+    light: Theme,
+    dark: Theme,
+}
+
+impl Highlighter {
+    /// Loads the syntaxes that come with syntect and those in
+    /// `FOLDER_SYNTAXES`, and the themes of `config`. Every language of
+    /// `config` needs a syntax.
+    pub(crate) fn new(config: &HighlightConfig) -> Result<Self, &'static str> {
+        let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+        if Path::new(FOLDER_SYNTAXES).exists() {
+            println!("Reading syntaxes in {:?}", FOLDER_SYNTAXES);
+            if let Err(e) = builder.add_from_folder(FOLDER_SYNTAXES, true) {
+                println!("{}", e);
+                return Err("Couldn't parse syntaxes.");
+            }
+        }
+        let syntaxes = builder.build();
+
+        let languages = config
+            .languages
+            .iter()
+            .map(|l| l.to_lowercase())
+            .collect::<Vec<_>>();
+        for language in &languages {
+            if syntaxes.find_syntax_by_token(language).is_none() {
+                println!("There is no syntax for {:?}.", language);
+                return Err("Couldn't find syntax of highlighted language.");
+            }
+        }
+
+        let mut themes = ThemeSet::load_defaults().themes;
+        let light = match themes.remove(&config.light) {
+            Some(t) => t,
+            None => return Err("Couldn't find light highlighting theme."),
+        };
+        let dark = match themes.remove(&config.dark) {
+            Some(t) => t,
+            None => return Err("Couldn't find dark highlighting theme."),
+        };
+
+        Ok(Self {
+            syntaxes,
+            languages,
+            style: config.style,
+            light,
+            dark,
+        })
+    }
+
+    /// Syntax of `lang`, if it's one of the languages that are highlighted.
+    fn syntax(&self, lang: Option<&str>) -> Option<&SyntaxReference> {
+        let lang = lang?.to_lowercase();
+        match self.languages.contains(&lang) {
+            true => self.syntaxes.find_syntax_by_token(&lang),
+            false => None,
+        }
+    }
+
+    /// Highlights `code` after `syntax`, in the style of the site configuration.
+    fn highlight_with(&self, syntax: &SyntaxReference, code: &str) -> Result<String, syntect::Error> {
+        match self.style {
+            HighlightStyle::Classes => {
+                let mut generator =
+                    ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, CLASS_STYLE);
+                for line in LinesWithEndings::from(code) {
+                    generator.parse_html_for_line_which_includes_newline(line)?;
+                }
+                Ok(generator.finalize())
+            }
+            HighlightStyle::Inline => {
+                let mut lines = HighlightLines::new(syntax, &self.light);
+                let mut html = String::new();
+                for line in LinesWithEndings::from(code) {
+                    let regions = lines.highlight_line(line, &self.syntaxes)?;
+                    html.push_str(&styled_line_to_highlighted_html(&regions, IncludeBackground::No)?);
+                }
+                Ok(html)
+            }
+        }
+    }
+
+    /// Converts `markdown` to HTML, with its code blocks highlighted.
+    pub(crate) fn markdown_to_html(&self, markdown: &str) -> String {
+        let mut plugins = ComrakPlugins::default();
+        plugins.render.codefence_syntax_highlighter = Some(self);
+        markdown_to_html_with_plugins(markdown, &ComrakOptions::default(), &plugins)
+    }
+
+    /// Writes the stylesheet of code highlighted with classes: the light
+    /// theme, and the dark theme for readers who prefer it. Printed pages keep
+    /// the light theme.
+    pub(crate) fn write_css(&self, css_path: &str) -> Result<(), &'static str> {
+        let (light, dark) = match (
+            css_for_theme_with_class_style(&self.light, CLASS_STYLE),
+            css_for_theme_with_class_style(&self.dark, CLASS_STYLE),
+        ) {
+            (Ok(l), Ok(d)) => (l, d),
+            _ => return Err("Couldn't convert highlighting themes to CSS."),
+        };
+
+        println!("Writing highlighting stylesheet {:?}", css_path);
+        let css = format!(
+            "{}\n@media screen and (prefers-color-scheme: dark) {{\n{}}}\n",
+            light, dark
+        );
+        match fs::write(css_path, css) {
+            Ok(_) => Ok(()),
+            _ => Err("Couldn't write highlighting stylesheet."),
+        }
+    }
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Opening tag of `tag` with `attributes`, in a fixed order, and `class`
+/// added to its classes.
+fn opening_tag(tag: &str, attributes: &HashMap<String, String>, class: Option<&str>) -> String {
+    let mut attributes = attributes.clone();
+    if let Some(class) = class {
+        let classes = attributes.entry(String::from("class")).or_default();
+        if !classes.is_empty() {
+            classes.push(' ');
+        }
+        classes.push_str(class);
+    }
+    let mut attributes = attributes.into_iter().collect::<Vec<_>>();
+    attributes.sort();
+
+    let mut opening = format!("<{}", tag);
+    for (name, value) in attributes {
+        opening.push_str(&format!(" {}='{}'", name, escape_html(&value)));
+    }
+    opening.push('>');
+    opening
+}
+
+impl SyntaxHighlighterAdapter for Highlighter {
+    fn highlight(&self, lang: Option<&str>, code: &str) -> String {
+        let syntax = match self.syntax(lang) {
+            Some(s) => s,
+            None => return escape_html(code),
+        };
+
+        // Code that syntect can't highlight, for instance because a syntax
+        // of `FOLDER_SYNTAXES` fails on it, is left as it is.
+        match self.highlight_with(syntax, code) {
+            Ok(html) => html,
+            Err(e) => {
+                println!("Couldn't highlight {:?} code: {}", syntax.name, e);
+                escape_html(code)
+            }
+        }
+    }
+
+    fn build_pre_tag(&self, attributes: &HashMap<String, String>) -> String {
+        match self.style {
+            HighlightStyle::Classes => opening_tag("pre", attributes, Some("hl-code")),
+            HighlightStyle::Inline => {
+                let settings = &self.light.settings;
+                let mut attributes = attributes.clone();
+                attributes.insert(
+                    String::from("style"),
+                    format!(
+                        "background-color:{};color:{}",
+                        hex(settings.background.unwrap_or(Color::WHITE)),
+                        hex(settings.foreground.unwrap_or(Color::BLACK))
+                    ),
+                );
+                opening_tag("pre", &attributes, None)
+            }
+        }
+    }
+
+    fn build_code_tag(&self, attributes: &HashMap<String, String>) -> String {
+        opening_tag("code", attributes, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlighter_only_allowed_languages() {
+        let highlighter = Highlighter::new(&HighlightConfig::default()).unwrap();
+        for lang in ["rust", "python", "qsharp", "openqasm", "bash"] {
+            assert!(highlighter.highlight(Some(lang), "let x = 1;\n").contains("<span class=\"hl-"));
+        }
+        assert_eq!(highlighter.highlight(Some("cobol"), "a < b\n"), "a &lt; b\n");
+        assert_eq!(highlighter.highlight(None, "a < b\n"), "a &lt; b\n");
+    }
+
+    #[test]
+    fn highlighter_inline_style_uses_light_theme() {
+        let config = HighlightConfig {
+            style: HighlightStyle::Inline,
+            ..HighlightConfig::default()
+        };
+        let highlighter = Highlighter::new(&config).unwrap();
+        let html = highlighter.highlight(Some("rust"), "let x = 1;\n");
+        assert!(html.contains("<span style=\"color:#"));
+        assert!(!html.contains("class="));
+        let background = hex(highlighter.light.settings.background.unwrap());
+        assert!(highlighter.build_pre_tag(&HashMap::new()).contains(&background));
+    }
+}
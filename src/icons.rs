@@ -0,0 +1,229 @@
+use std::fs;
+use std::fs::File;
+
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{ColorType, ImageEncoder, Rgba, RgbaImage};
+
+use super::images::hex_to_rgb;
+use super::site::SiteConfig;
+
+pub(crate) const FILE_FAVICON: &str = "favicon.ico";
+pub(crate) const FILE_WEB_MANIFEST: &str = "site.webmanifest";
+pub(crate) const FOLDER_ICONS: &str = "icons";
+
+/// Sizes, in pixels, of the images in the favicon.
+const FAVICON_SIZES: [u32; 3] = [16, 32, 48];
+/// Sizes, in pixels, of the PNG icons: 32 for browser tabs, and 192 and 512
+/// for the home screens and splash screens of installed apps.
+const ICON_SIZES: [u32; 3] = [32, 192, 512];
+const APPLE_TOUCH_ICON_SIZE: u32 = 180;
+const MASKABLE_ICON_SIZE: u32 = 512;
+/// Fraction of a maskable icon the image fills, so that it stays in the
+/// safe zone that platforms never mask.
+const MASKABLE_ICON_SCALE: f32 = 0.8;
+
+/// Makes every icon of the site from the image in the config, and writes the
+/// web app manifest that lists them.
+pub(crate) fn make_icons(config: &SiteConfig) -> Result<(), &'static str> {
+    let icons = &config.icons;
+    let background = match hex_to_rgb(&config.manifest.background_color) {
+        Some([r, g, b]) => Rgba([r, g, b, 255]),
+        None => return Err("Couldn't parse manifest background color."),
+    };
+
+    let source = match image::open(&icons.source) {
+        Ok(img) => img.to_rgba8(),
+        _ => return Err("Couldn't open icon image."),
+    };
+    // Icons are square, so the image is cropped to its center.
+    let side = source.width().min(source.height());
+    let square = image::imageops::crop_imm(
+        &source,
+        (source.width() - side) / 2,
+        (source.height() - side) / 2,
+        side,
+        side,
+    )
+    .to_image();
+
+    let icon = |size: u32| {
+        let img = image::imageops::resize(&square, size, size, FilterType::Lanczos3);
+        match icons.circular {
+            true => crop_to_circle(img),
+            false => img,
+        }
+    };
+
+    if fs::create_dir_all(FOLDER_ICONS).is_err() {
+        return Err("Couldn't create icons folder.");
+    }
+
+    let favicon = FAVICON_SIZES
+        .iter()
+        .map(|size| encode_png(&icon(*size)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let frames = favicon
+        .iter()
+        .zip(FAVICON_SIZES)
+        .map(|(png, size)| IcoFrame::with_encoded(png, size, size, ColorType::Rgba8))
+        .collect::<Result<Vec<_>, _>>();
+    let written = match (frames, File::create(FILE_FAVICON)) {
+        (Ok(frames), Ok(file)) => IcoEncoder::new(file).encode_images(&frames).is_ok(),
+        _ => false,
+    };
+    if !written {
+        return Err("Couldn't write favicon.");
+    }
+
+    for size in ICON_SIZES {
+        write_png(&icon(size), &icon_path(&format!("icon-{}", size)))?;
+    }
+
+    // Platforms crop these two themselves, and show transparency as black.
+    let apple_touch_icon = image::imageops::resize(
+        &square,
+        APPLE_TOUCH_ICON_SIZE,
+        APPLE_TOUCH_ICON_SIZE,
+        FilterType::Lanczos3,
+    );
+    write_png(
+        &over_background(&apple_touch_icon, APPLE_TOUCH_ICON_SIZE, background),
+        &icon_path("apple-touch-icon"),
+    )?;
+
+    let inner = (MASKABLE_ICON_SIZE as f32 * MASKABLE_ICON_SCALE).round() as u32;
+    let maskable = image::imageops::resize(&square, inner, inner, FilterType::Lanczos3);
+    write_png(
+        &over_background(&maskable, MASKABLE_ICON_SIZE, background),
+        &icon_path("icon-maskable"),
+    )?;
+
+    write_manifest(config)
+}
+
+/// Path of the PNG icon called `name`.
+pub(crate) fn icon_path(name: &str) -> String {
+    format!("{}/{}.png", FOLDER_ICONS, name)
+}
+
+/// Makes the pixels of `img` outside of the circle it is the square of
+/// transparent, smoothing the edge over a pixel.
+fn crop_to_circle(mut img: RgbaImage) -> RgbaImage {
+    let radius = img.width() as f32 / 2.;
+    for (x, y, px) in img.enumerate_pixels_mut() {
+        let distance = ((x as f32 + 0.5 - radius).powi(2) + (y as f32 + 0.5 - radius).powi(2)).sqrt();
+        let coverage = (radius - distance + 0.5).clamp(0., 1.);
+        px.0[3] = (px.0[3] as f32 * coverage).round() as u8;
+    }
+    img
+}
+
+/// Centers `img` on an opaque square `size` pixels wide of `background`.
+fn over_background(img: &RgbaImage, size: u32, background: Rgba<u8>) -> RgbaImage {
+    let mut canvas = RgbaImage::from_pixel(size, size, background);
+    image::imageops::overlay(
+        &mut canvas,
+        img,
+        ((size - img.width()) / 2) as i64,
+        ((size - img.height()) / 2) as i64,
+    );
+    canvas
+}
+
+fn encode_png(img: &RgbaImage) -> Result<Vec<u8>, &'static str> {
+    let mut png = Vec::new();
+    match PngEncoder::new(&mut png).write_image(img, img.width(), img.height(), ColorType::Rgba8) {
+        Ok(_) => Ok(png),
+        _ => Err("Couldn't encode icon."),
+    }
+}
+
+fn write_png(img: &RgbaImage, path: &str) -> Result<(), &'static str> {
+    match fs::write(path, encode_png(img)?) {
+        Ok(_) => Ok(()),
+        _ => Err("Couldn't write icon."),
+    }
+}
+
+/// Web app manifest, which tells browsers the name, colors and icons of the
+/// site when it is installed as an app.
+fn manifest_json(config: &SiteConfig) -> serde_json::Value {
+    let manifest = &config.manifest;
+    let mut icons = ICON_SIZES
+        .iter()
+        .filter(|size| **size >= 192)
+        .map(|size| {
+            serde_json::json!({
+                "src": icon_path(&format!("icon-{}", size)),
+                "sizes": format!("{0}x{0}", size),
+                "type": "image/png",
+            })
+        })
+        .collect::<Vec<_>>();
+    icons.push(serde_json::json!({
+        "src": icon_path("icon-maskable"),
+        "sizes": format!("{0}x{0}", MASKABLE_ICON_SIZE),
+        "type": "image/png",
+        "purpose": "maskable",
+    }));
+
+    serde_json::json!({
+        "name": manifest.name,
+        "short_name": manifest.short_name,
+        "start_url": "index.html",
+        "display": "standalone",
+        "theme_color": manifest.theme_color,
+        "background_color": manifest.background_color,
+        "icons": icons,
+    })
+}
+
+fn write_manifest(config: &SiteConfig) -> Result<(), &'static str> {
+    let json = manifest_json(config);
+    match fs::write(FILE_WEB_MANIFEST, serde_json::to_string_pretty(&json).unwrap()) {
+        Ok(_) => Ok(()),
+        _ => Err("Couldn't write web app manifest."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_lists_app_icons() {
+        let json = manifest_json(&SiteConfig::default());
+        assert_eq!(json["theme_color"], "#92D4AB");
+        assert_eq!(json["start_url"], "index.html");
+        let icons = json["icons"].as_array().unwrap();
+        let sources = icons.iter().map(|i| i["src"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(sources, ["icons/icon-192.png", "icons/icon-512.png", "icons/icon-maskable.png"]);
+        assert_eq!(icons[2]["sizes"], "512x512");
+        assert_eq!(icons[2]["purpose"], "maskable");
+    }
+
+    #[test]
+    fn circle_clears_the_corners() {
+        let img = crop_to_circle(RgbaImage::from_pixel(32, 32, Rgba([146, 212, 171, 255])));
+        assert_eq!(img.get_pixel(0, 0).0[3], 0);
+        assert_eq!(img.get_pixel(31, 31).0[3], 0);
+        assert_eq!(img.get_pixel(16, 16).0, [146, 212, 171, 255]);
+        assert_eq!(img.get_pixel(16, 2).0[3], 255);
+        // The edge is smoothed.
+        assert!((1..255).contains(&img.get_pixel(16, 0).0[3]));
+    }
+
+    #[test]
+    fn icon_centered_over_background() {
+        let background = Rgba([235, 233, 228, 255]);
+        let img = RgbaImage::from_pixel(8, 8, Rgba([146, 212, 171, 255]));
+        let canvas = over_background(&img, 10, background);
+        assert_eq!(canvas.dimensions(), (10, 10));
+        assert_eq!(*canvas.get_pixel(0, 0), background);
+        assert_eq!(*canvas.get_pixel(9, 9), background);
+        assert_eq!(canvas.get_pixel(1, 1).0, [146, 212, 171, 255]);
+        assert_eq!(canvas.get_pixel(8, 8).0, [146, 212, 171, 255]);
+    }
+}
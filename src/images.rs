@@ -0,0 +1,1152 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::SystemTime;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+use mozjpeg::{ColorSpace, Compress};
+
+use rayon::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use sha2::{Digest, Sha256};
+
+use super::escape_html;
+use super::site::{CompressionConfig, SiteConfig};
+
+/// Widths, in pixels, of the variants made of every image. Images narrower
+/// than a width are not upscaled, and get a variant of their own width.
+const IMAGE_WIDTHS: [u32; 3] = [480, 960, 1920];
+const IMAGE_QUALITY: f32 = 69.;
+const AVIF_SPEED: u8 = 8;
+
+/// Width, in pixels, of the blurred placeholder inlined in pages while the
+/// image loads.
+const PLACEHOLDER_WIDTH: u32 = 24;
+const PLACEHOLDER_QUALITY: u8 = 50;
+
+/// Enum to enumerate the formats every image is encoded to, from the most to
+/// the least efficient, which is the order browsers should try them in:
+/// `Avif` and `Webp` for browsers that support them;
+/// `Jpeg` as a fallback for every other browser.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) enum ImageFormat {
+    #[serde(rename = "image/avif")]
+    Avif,
+    #[serde(rename = "image/webp")]
+    Webp,
+    #[serde(rename = "image/jpeg")]
+    Jpeg,
+}
+
+impl ImageFormat {
+    const ALL: [ImageFormat; 3] = [ImageFormat::Avif, ImageFormat::Webp, ImageFormat::Jpeg];
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "avif",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+
+    fn mime(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    fn encode(&self, img: &DynamicImage) -> Result<Vec<u8>, &'static str> {
+        let rgb = img.to_rgb8();
+        match self {
+            ImageFormat::Avif => {
+                let pixels = rgb
+                    .pixels()
+                    .map(|p| ravif::RGB8::new(p[0], p[1], p[2]))
+                    .collect::<Vec<_>>();
+                let img = ravif::Img::new(pixels.as_slice(), rgb.width() as usize, rgb.height() as usize);
+                match ravif::Encoder::new()
+                    .with_quality(IMAGE_QUALITY)
+                    .with_speed(AVIF_SPEED)
+                    .encode_rgb(img)
+                {
+                    Ok(encoded) => Ok(encoded.avif_file),
+                    _ => Err("Couldn't encode image to AVIF."),
+                }
+            }
+            ImageFormat::Webp => {
+                let encoded = webp::Encoder::from_rgb(&rgb, rgb.width(), rgb.height()).encode(IMAGE_QUALITY);
+                Ok(encoded.to_vec())
+            }
+            ImageFormat::Jpeg => {
+                let mut bytes = Vec::new();
+                let mut encoder =
+                    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, IMAGE_QUALITY as u8);
+                match encoder.encode_image(&rgb) {
+                    Ok(_) => Ok(bytes),
+                    _ => Err("Couldn't encode image to JPEG."),
+                }
+            }
+        }
+    }
+}
+
+/// Struct that represents an image resized to several widths and encoded to
+/// every `ImageFormat`, so that browsers download only what they need.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ResponsiveImage {
+    pub(crate) name: String,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    variants: Vec<ImageVariant>,
+    /// Data URI of a tiny, blurred version of the image.
+    #[serde(skip)]
+    pub(crate) placeholder: String,
+    /// Average color of the image, shown before even the placeholder.
+    #[serde(skip)]
+    pub(crate) color: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ImageVariant {
+    path: String,
+    width: u32,
+    format: ImageFormat,
+}
+
+impl ResponsiveImage {
+    /// Makes the variants of the image at `image_path` in `to_dest_dir`. Their
+    /// names include a hash of the source, so variants that already exist are
+    /// up to date and aren't made again, and those of older versions of the
+    /// image are removed.
+    pub(crate) fn from_file(image_path: &str, to_dest_dir: &str) -> Result<Self, &'static str> {
+        let source = match fs::read(image_path) {
+            Ok(s) => s,
+            _ => return Err("Couldn't read image file."),
+        };
+        let hash = format!("{:x}", Sha256::digest(&source));
+        let name = match Path::new(image_path).file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            _ => return Err("Couldn't find image file name."),
+        };
+
+        let (width, height) = match image::io::Reader::new(std::io::Cursor::new(&source))
+            .with_guessed_format()
+            .ok()
+            .and_then(|r| r.into_dimensions().ok())
+        {
+            Some(d) => d,
+            _ => return Err("Couldn't read image dimensions."),
+        };
+
+        let mut widths = IMAGE_WIDTHS
+            .iter()
+            .copied()
+            .filter(|w| *w < width)
+            .collect::<Vec<_>>();
+        if widths.len() < IMAGE_WIDTHS.len() {
+            widths.push(width);
+        }
+
+        if fs::create_dir_all(to_dest_dir).is_err() {
+            return Err("Couldn't create folder.");
+        }
+
+        let mut variants = Vec::new();
+        let mut decoded: Option<DynamicImage> = None;
+        for variant_width in widths {
+            let mut resized: Option<DynamicImage> = None;
+            for format in ImageFormat::ALL {
+                let path = format!(
+                    "{}/{}-{}-{}.{}",
+                    to_dest_dir,
+                    name,
+                    &hash[..12],
+                    variant_width,
+                    format.extension()
+                );
+
+                if !Path::new(&path).exists() {
+                    println!("Writing image variant {:?}", path);
+                    decode(&mut decoded, &source)?;
+                    let img = resized.get_or_insert_with(|| {
+                        let img = decoded.as_ref().unwrap();
+                        if variant_width == width {
+                            img.clone()
+                        } else {
+                            img.resize(variant_width, u32::MAX, FilterType::Lanczos3)
+                        }
+                    });
+                    if fs::write(&path, format.encode(img)?).is_err() {
+                        return Err("Couldn't write image file.");
+                    }
+                }
+
+                variants.push(ImageVariant {
+                    path,
+                    width: variant_width,
+                    format,
+                });
+            }
+        }
+
+        let placeholder_path = format!("{}/{}-{}-placeholder.jpg", to_dest_dir, name, &hash[..12]);
+        if !Path::new(&placeholder_path).exists() {
+            println!("Writing image placeholder {:?}", placeholder_path);
+            let tiny = decode(&mut decoded, &source)?
+                .thumbnail(PLACEHOLDER_WIDTH, u32::MAX)
+                .blur(1.)
+                .to_rgb8();
+            let mut bytes = Vec::new();
+            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, PLACEHOLDER_QUALITY);
+            if encoder.encode_image(&tiny).is_err() || fs::write(&placeholder_path, &bytes).is_err() {
+                return Err("Couldn't write image placeholder.");
+            }
+        }
+
+        remove_superseded_variants(to_dest_dir, &name, &hash[..12]);
+
+        let placeholder = match fs::read(&placeholder_path) {
+            Ok(p) => p,
+            _ => return Err("Couldn't read image placeholder."),
+        };
+        let color = match image::load_from_memory(&placeholder) {
+            Ok(tiny) => {
+                let tiny = tiny.to_rgb8();
+                let mut sum = [0u64; 3];
+                for px in tiny.pixels() {
+                    for (s, c) in sum.iter_mut().zip(px.0) {
+                        *s += c as u64;
+                    }
+                }
+                let n = (tiny.width() * tiny.height()).max(1) as u64;
+                format!("#{:02x}{:02x}{:02x}", sum[0] / n, sum[1] / n, sum[2] / n)
+            }
+            _ => return Err("Couldn't decode image placeholder."),
+        };
+
+        Ok(Self {
+            name,
+            width,
+            height,
+            variants,
+            placeholder: format!("data:image/jpeg;base64,{}", BASE64.encode(&placeholder)),
+            color,
+        })
+    }
+
+    /// Makes the variants of every image of `file_names` in `from_dir`.
+    pub(crate) fn from_images(
+        from_dir: &str,
+        file_names: &[String],
+        to_dest_dir: &str,
+    ) -> Result<Vec<Self>, &'static str> {
+        file_names
+            .par_iter()
+            .map(|name| Self::from_file(&format!("{}/{}", from_dir, name), to_dest_dir))
+            .collect()
+    }
+
+    /// Paths of every variant of the image.
+    pub(crate) fn paths(&self) -> impl Iterator<Item = &str> {
+        self.variants.iter().map(|v| v.path.as_str())
+    }
+
+    /// Variant of `format` to show in a box `display_width` pixels wide on a
+    /// screen with `density` pixels per CSS pixel.
+    fn variant(&self, format: ImageFormat, display_width: u32, density: u32) -> &ImageVariant {
+        let mut of_format = self.variants.iter().filter(|v| v.format == format);
+        of_format
+            .clone()
+            .find(|v| v.width >= display_width * density)
+            .or_else(|| of_format.next_back())
+            .unwrap()
+    }
+
+    /// Path of the JPEG variant to show in a box `display_width` pixels wide,
+    /// for places that take a single image, like sharing previews.
+    pub(crate) fn jpeg_path(&self, display_width: u32) -> &str {
+        &self.variant(ImageFormat::Jpeg, display_width, 1).path
+    }
+
+    /// Returns the `srcset` of the variants of `format`.
+    fn srcset(&self, format: ImageFormat) -> String {
+        self.variants
+            .iter()
+            .filter(|v| v.format == format)
+            .map(|v| format!("{} {}w", v.path, v.width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Writes a `<picture>` with a source per format, leaving the browser to
+    /// pick the width it needs given `sizes`.
+    pub(crate) fn write_picture(&self, w: &mut dyn Write, alt: &str, sizes: &str, class: &str) {
+        write!(w, "<picture>").unwrap();
+        for format in [ImageFormat::Avif, ImageFormat::Webp] {
+            write!(
+                w,
+                "<source type='{}' srcset='{}' sizes='{}'>",
+                format.mime(),
+                self.srcset(format),
+                sizes
+            )
+            .unwrap();
+        }
+        write!(
+            w,
+            "<img class='{}' src='{}' srcset='{}' sizes='{}' width='{}' height='{}' alt='{}' \
+            loading='lazy' decoding='async' style='{}background-size:cover'></picture>",
+            class,
+            self.variant(ImageFormat::Jpeg, self.width, 1).path,
+            self.srcset(ImageFormat::Jpeg),
+            sizes,
+            self.width,
+            self.height,
+            escape_html(alt),
+            self.placeholder_style()
+        )
+        .unwrap();
+    }
+
+    /// Returns CSS declarations that show the average color and then the
+    /// placeholder of the image while it loads.
+    pub(crate) fn placeholder_style(&self) -> String {
+        format!(
+            "background-color:{};background-image:url(\"{}\");",
+            self.color, self.placeholder
+        )
+    }
+
+    /// Returns CSS declarations that set the image as a background of a box
+    /// `display_width` pixels wide: a JPEG for every browser, then an
+    /// `image-set()` that browsers which support it use instead, both over
+    /// the placeholder.
+    pub(crate) fn background_style(&self, display_width: u32) -> String {
+        let image_set = ImageFormat::ALL
+            .iter()
+            .flat_map(|format| {
+                [1, 2].map(|density| {
+                    format!(
+                        "url(\"{}\") type(\"{}\") {}x",
+                        self.variant(*format, display_width, density).path,
+                        format.mime(),
+                        density
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The placeholder is the bottom layer, so it shows until the image
+        // above it loads.
+        format!(
+            "background-color:{color};\
+            background-image:url(\"{}\"), url(\"{placeholder}\");\
+            background-image:image-set({}), url(\"{placeholder}\");",
+            self.variant(ImageFormat::Jpeg, display_width, 1).path,
+            image_set.join(", "),
+            color = self.color,
+            placeholder = self.placeholder
+        )
+    }
+}
+
+/// Removes the variants and placeholder of the image called `name` in `dir`
+/// whose hash isn't `hash`, i.e. those of older versions of the image.
+fn remove_superseded_variants(dir: &str, name: &str, hash: &str) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        _ => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        // Names are `{name}-{hash}-{width}.{extension}`, or end with
+        // `-placeholder.jpg`, and other images may share a prefix of `name`.
+        let superseded = file_name
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .and_then(|rest| rest.split_once('-'))
+            .and_then(|(old_hash, rest)| Some((old_hash, rest.split_once('.')?.0)))
+            .is_some_and(|(old_hash, suffix)| {
+                old_hash != hash
+                    && old_hash.len() == hash.len()
+                    && old_hash.chars().all(|c| c.is_ascii_hexdigit())
+                    && (suffix == "placeholder" || suffix.parse::<u32>().is_ok())
+            });
+        if superseded {
+            println!("Removing superseded image variant {:?}", file_name);
+            if fs::remove_file(entry.path()).is_err() {
+                println!("Couldn't remove {:?}.", file_name);
+            }
+        }
+    }
+}
+
+/// Struct that represents the background images pages pick from at random:
+/// `light` ones for the default theme, and `dark` ones for readers who
+/// prefer a dark one.
+#[derive(Debug, Default)]
+pub(crate) struct Backgrounds {
+    pub(crate) light: Vec<ResponsiveImage>,
+    pub(crate) dark: Vec<ResponsiveImage>,
+    /// Credits of the backgrounds, by image name.
+    pub(crate) credits: HashMap<String, Credit>,
+}
+
+/// Struct that represents who took a background image, and where.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Credit {
+    pub(crate) photographer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) location: Option<String>,
+}
+
+impl Backgrounds {
+    /// Makes the variants of every background in `to_dest_dir`. Backgrounds
+    /// are the images of the overlay source, so that images that were
+    /// removed from it aren't shown even if their overlaid copies remain.
+    pub(crate) fn from_config(config: &SiteConfig, to_dest_dir: &str) -> Result<Self, &'static str> {
+        let overlay = &config.overlay;
+        let exclude = &config.backgrounds.exclude;
+
+        let paths = match fs::read_dir(&overlay.source) {
+            Ok(p) => p,
+            _ => return Err("Couldn't open folder."),
+        };
+        let mut file_names = paths
+            .filter_map(|p| p.ok().map(|p| p.file_name().to_string_lossy().into_owned()))
+            .filter(|name| image::ImageFormat::from_path(name).is_ok() && !exclude.contains(name))
+            // Images the overlay failed on are left out.
+            .filter(|name| Path::new(&overlay.light.dest).join(name).exists())
+            .collect::<Vec<_>>();
+        file_names.sort();
+
+        let light = ResponsiveImage::from_images(
+            &overlay.light.dest,
+            &file_names,
+            &format!("{}/backgrounds", to_dest_dir),
+        )?;
+        let dark = match &overlay.dark {
+            Some(dark) => ResponsiveImage::from_images(
+                &dark.dest,
+                &file_names,
+                &format!("{}/dark-backgrounds", to_dest_dir),
+            )?,
+            None => Vec::new(),
+        };
+
+        let mut credits = HashMap::new();
+        for (file_name, credit) in &config.backgrounds.credits {
+            if !file_names.contains(file_name) {
+                println!("Couldn't find background {:?} to credit.", file_name);
+                continue;
+            }
+            let name = Path::new(file_name).file_stem().unwrap().to_string_lossy();
+            credits.insert(name.into_owned(), credit.clone());
+        }
+
+        Ok(Self { light, dark, credits })
+    }
+
+    /// Writes the variants and credits of every background to a script,
+    /// which pages use once they are loaded to show the background that fits
+    /// the screen.
+    pub(crate) fn write_script(&self, script_path: &str) -> Result<(), &'static str> {
+        let script = format!(
+            "var backgroundImages = {};\nvar darkBackgroundImages = {};\nvar backgroundCredits = {};",
+            serde_json::to_string(&self.light).unwrap(),
+            serde_json::to_string(&self.dark).unwrap(),
+            serde_json::to_string(&self.credits).unwrap()
+        );
+        match fs::write(script_path, script) {
+            Ok(_) => Ok(()),
+            _ => Err("Couldn't write backgrounds script."),
+        }
+    }
+
+    /// Writes the script that picks the background of the page and shows its
+    /// placeholder right away. It belongs in the head, so that it runs
+    /// before the page is first painted.
+    pub(crate) fn write_placeholder_script(&self, w: &mut dyn Write) {
+        let placeholders = |images: &[ResponsiveImage]| {
+            serde_json::to_string(
+                &images
+                    .iter()
+                    .map(|image| [&image.color, &image.placeholder])
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap()
+        };
+
+        write!(
+            w,
+            "
+        var backgroundPlaceholders = {};
+        var darkBackgroundPlaceholders = {};
+        var backgroundDark = darkBackgroundPlaceholders.length > 0 && window.matchMedia
+            && window.matchMedia('(prefers-color-scheme: dark)').matches;
+        if (backgroundDark) {{
+            backgroundPlaceholders = darkBackgroundPlaceholders;
+        }}
+        var backgroundIndex = Math.floor(Math.random() * backgroundPlaceholders.length);
+        if (backgroundPlaceholders.length > 0) {{
+            document.documentElement.style.setProperty('--background-color', backgroundPlaceholders[backgroundIndex][0]);
+            document.documentElement.style.setProperty('--background-placeholder', 'url(\"' + backgroundPlaceholders[backgroundIndex][1] + '\")');
+        }}
+        ",
+            placeholders(&self.light),
+            placeholders(&self.dark)
+        )
+        .unwrap();
+    }
+}
+
+/// Parses a `#rrggbb` color.
+pub(crate) fn hex_to_rgb(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let mut rgb = [0; 3];
+    for (i, c) in rgb.iter_mut().enumerate() {
+        *c = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(rgb)
+}
+
+/// Decodes `source` the first time it is needed.
+fn decode<'a>(decoded: &'a mut Option<DynamicImage>, source: &[u8]) -> Result<&'a DynamicImage, &'static str> {
+    if decoded.is_none() {
+        *decoded = match image::load_from_memory(source) {
+            Ok(img) => Some(img),
+            _ => return Err("Couldn't decode image."),
+        };
+    }
+    Ok(decoded.as_ref().unwrap())
+}
+
+/// Enum to enumerate how the color of an `Overlay` is mixed with an image:
+/// `Normal` paints it over the image;
+/// `Multiply` darkens the image with it;
+/// `Screen` lightens the image with it;
+/// `Overlay` multiplies the shadows and screens the highlights.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+impl BlendMode {
+    fn blend(&self, base: f32, color: f32) -> f32 {
+        match self {
+            BlendMode::Normal => color,
+            BlendMode::Multiply => base * color,
+            BlendMode::Screen => 1. - (1. - base) * (1. - color),
+            BlendMode::Overlay if base < 0.5 => 2. * base * color,
+            BlendMode::Overlay => 1. - 2. * (1. - base) * (1. - color),
+        }
+    }
+}
+
+/// Struct that represents a color laid over images with some `opacity`,
+/// whose results are written to `dest`.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Overlay {
+    pub(crate) dest: String,
+    color: String,
+    opacity: f32,
+    #[serde(default)]
+    blend: BlendMode,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            dest: String::from("white-img"),
+            color: String::from("#FFFFFF"),
+            opacity: 0.9,
+            blend: BlendMode::Normal,
+        }
+    }
+}
+
+impl Overlay {
+    fn rgb(&self) -> Result<[f32; 3], &'static str> {
+        match hex_to_rgb(&self.color) {
+            Some(rgb) => Ok(rgb.map(|c| c as f32 / 255.)),
+            None => Err("Couldn't parse overlay color."),
+        }
+    }
+
+    fn apply(&self, source_path: &Path, dest_path: &Path, rgb: [f32; 3]) -> Result<(), String> {
+        let mut img = image::open(source_path).map_err(|e| e.to_string())?.to_rgb8();
+        let opacity = self.opacity.clamp(0., 1.);
+        for px in img.pixels_mut() {
+            for (channel, color) in px.0.iter_mut().zip(rgb) {
+                let base = *channel as f32 / 255.;
+                let mixed = base * (1. - opacity) + self.blend.blend(base, color) * opacity;
+                *channel = (mixed * 255.).round() as u8;
+            }
+        }
+        img.save(dest_path).map_err(|e| e.to_string())
+    }
+}
+
+/// Struct that collects what a stage did with every file of a folder, so
+/// that one bad file is reported at the end instead of stopping the build.
+#[derive(Debug)]
+pub(crate) struct ImageReport {
+    stage: String,
+    done: Vec<String>,
+    unchanged: Vec<String>,
+    not_images: Vec<String>,
+    errors: Vec<(String, String)>,
+}
+
+impl ImageReport {
+    fn new(stage: &str) -> Self {
+        Self {
+            stage: stage.to_string(),
+            done: Vec::new(),
+            unchanged: Vec::new(),
+            not_images: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub(crate) fn print(&self) {
+        println!(
+            "{}: {} done, {} unchanged, {} not images, {} failed.",
+            self.stage,
+            self.done.len(),
+            self.unchanged.len(),
+            self.not_images.len(),
+            self.errors.len()
+        );
+        for path in &self.not_images {
+            println!("  Skipped {:?}, which isn't an image.", path);
+        }
+        for (path, error) in &self.errors {
+            println!("  Couldn't process {:?}: {}", path, error);
+        }
+    }
+}
+
+/// Enum to enumerate what happened to a file of a stage.
+enum ImageOutcome {
+    Done,
+    Unchanged,
+    NotImage,
+}
+
+/// Lays `overlay` over every image in `from_dest_dir`, in parallel. Images
+/// whose output is newer than both the image and `settings_modified` are
+/// left as they are.
+pub(crate) fn apply_overlay_to_images(
+    from_dest_dir: &str,
+    overlay: &Overlay,
+    settings_modified: Option<SystemTime>,
+) -> Result<ImageReport, &'static str> {
+    let rgb = overlay.rgb()?;
+    let paths = match fs::read_dir(from_dest_dir) {
+        Ok(p) => p.filter_map(|p| p.ok().map(|p| p.path())).collect::<Vec<_>>(),
+        _ => return Err("Couldn't open folder."),
+    };
+    if fs::create_dir_all(&overlay.dest).is_err() {
+        return Err("Couldn't create folder.");
+    }
+
+    let outcomes = paths
+        .par_iter()
+        .map(|source_path| {
+            if !source_path.is_file() || image::ImageFormat::from_path(source_path).is_err() {
+                return Ok(ImageOutcome::NotImage);
+            }
+
+            let dest_path = PathBuf::from(&overlay.dest).join(source_path.file_name().unwrap());
+            let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+            let up_to_date = match (modified(&dest_path), modified(source_path)) {
+                (Some(dest), Some(source)) => dest >= source && settings_modified.is_none_or(|s| dest >= s),
+                _ => false,
+            };
+            if up_to_date {
+                return Ok(ImageOutcome::Unchanged);
+            }
+
+            println!("Overlaying image {:?}", dest_path);
+            overlay.apply(source_path, &dest_path, rgb)?;
+            Ok(ImageOutcome::Done)
+        })
+        .collect::<Vec<Result<ImageOutcome, String>>>();
+
+    let mut report = ImageReport::new(&format!("Overlay {:?}", overlay.dest));
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        let path = format!("{}", path.display());
+        match outcome {
+            Ok(ImageOutcome::Done) => report.done.push(path),
+            Ok(ImageOutcome::Unchanged) => report.unchanged.push(path),
+            Ok(ImageOutcome::NotImage) => report.not_images.push(path),
+            Err(e) => report.errors.push((path, e)),
+        }
+    }
+    Ok(report)
+}
+
+/// Struct that represents the outcome of compressing one image, sent as
+/// soon as it is done so that progress can be shown.
+#[derive(Debug)]
+pub(crate) struct CompressionEvent {
+    file: String,
+    bytes_before: u64,
+    /// Size of the compressed image, `None` when it was already up to date.
+    bytes_after: Result<Option<u64>, String>,
+}
+
+/// Struct that collects the outcome of compressing every image of a folder.
+#[derive(Debug)]
+pub(crate) struct CompressionReport {
+    events: Vec<CompressionEvent>,
+}
+
+impl CompressionReport {
+    pub(crate) fn has_errors(&self) -> bool {
+        self.events.iter().any(|e| e.bytes_after.is_err())
+    }
+
+    /// Prints a table with the size of every image before and after, and
+    /// the totals of the images that were compressed.
+    pub(crate) fn print(&self) {
+        println!("{:<32} {:>10} {:>10} {:>7}", "File", "Before", "After", "Ratio");
+        let (mut before, mut after) = (0, 0);
+        for event in &self.events {
+            match &event.bytes_after {
+                Ok(Some(bytes_after)) => {
+                    println!(
+                        "{:<32} {:>10} {:>10} {:>6.1}%",
+                        event.file,
+                        format_bytes(event.bytes_before),
+                        format_bytes(*bytes_after),
+                        ratio(event.bytes_before, *bytes_after)
+                    );
+                    before += event.bytes_before;
+                    after += bytes_after;
+                }
+                Ok(None) => println!(
+                    "{:<32} {:>10} {:>10} {:>7}",
+                    event.file,
+                    format_bytes(event.bytes_before),
+                    "-",
+                    "unchanged"
+                ),
+                Err(e) => println!(
+                    "{:<32} {:>10} {:>10} {:>7}  {}",
+                    event.file,
+                    format_bytes(event.bytes_before),
+                    "-",
+                    "FAILED",
+                    e
+                ),
+            }
+        }
+        println!(
+            "{:<32} {:>10} {:>10} {:>6.1}%",
+            "Total",
+            format_bytes(before),
+            format_bytes(after),
+            ratio(before, after)
+        );
+
+        let failed = self.events.iter().filter(|e| e.bytes_after.is_err()).count();
+        if failed > 0 {
+            println!("{} of {} images failed to compress.", failed, self.events.len());
+        }
+    }
+}
+
+fn ratio(bytes_before: u64, bytes_after: u64) -> f64 {
+    if bytes_before == 0 {
+        0.
+    } else {
+        100. * bytes_after as f64 / bytes_before as f64
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+/// Encodes `img` as an optimized JPEG of `quality`.
+fn encode_jpeg(img: &DynamicImage, quality: f32) -> Result<Vec<u8>, String> {
+    let rgb = img.to_rgb8();
+    // mozjpeg reports the errors of libjpeg by panicking.
+    let encoded = std::panic::catch_unwind(|| {
+        let mut compress = Compress::new(ColorSpace::JCS_RGB);
+        compress.set_size(rgb.width() as usize, rgb.height() as usize);
+        compress.set_quality(quality);
+        compress.set_optimize_scans(true);
+        let mut started = compress.start_compress(Vec::new())?;
+        started.write_scanlines(rgb.as_raw())?;
+        started.finish()
+    });
+    match encoded {
+        Ok(e) => e.map_err(|e| e.to_string()),
+        _ => Err(String::from("Couldn't encode JPEG.")),
+    }
+}
+
+/// Compresses `source_path` to a JPEG in `config.dest`, with the quality and
+/// size ratio `config` gives it, and returns the size of the result, or
+/// `None` if it was newer than the image and `settings_modified`.
+fn compress_image(
+    source_path: &Path,
+    config: &CompressionConfig,
+    settings_modified: Option<SystemTime>,
+) -> Result<Option<u64>, String> {
+    let file_name = source_path.file_name().unwrap().to_string_lossy();
+    let (quality, size_ratio) = config.factor(&file_name)?;
+    let dest_path = Path::new(&config.dest).join(source_path.with_extension("jpg").file_name().unwrap());
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    let up_to_date = match (modified(&dest_path), modified(source_path)) {
+        (Some(dest), Some(source)) => dest >= source && settings_modified.is_none_or(|s| dest >= s),
+        _ => false,
+    };
+    if up_to_date {
+        return Ok(None);
+    }
+
+    let mut img = image::open(source_path).map_err(|e| e.to_string())?;
+    if size_ratio < 1. {
+        let width = (img.width() as f32 * size_ratio).round().max(1.) as u32;
+        let height = (img.height() as f32 * size_ratio).round().max(1.) as u32;
+        img = img.resize(width, height, FilterType::Triangle);
+    }
+    let jpeg = encode_jpeg(&img, quality)?;
+    fs::write(&dest_path, &jpeg).map_err(|e| e.to_string())?;
+    Ok(Some(jpeg.len() as u64))
+}
+
+/// Compresses every image in `from_dest_dir` to a JPEG in `config.dest`, in
+/// parallel, with the quality `config` gives it. Progress is printed as
+/// every image is done, and images whose output is newer than both the image
+/// and `settings_modified` are left as they are.
+pub(crate) fn compress_images(
+    from_dest_dir: &str,
+    config: &CompressionConfig,
+    settings_modified: Option<SystemTime>,
+) -> Result<CompressionReport, &'static str> {
+    let mut paths = match fs::read_dir(from_dest_dir) {
+        Ok(p) => p
+            .filter_map(|p| p.ok().map(|p| p.path()))
+            .filter(|p| p.is_file() && image::ImageFormat::from_path(p).is_ok())
+            .collect::<Vec<_>>(),
+        _ => return Err("Couldn't open folder."),
+    };
+    paths.sort();
+    if fs::create_dir_all(&config.dest).is_err() {
+        return Err("Couldn't create folder.");
+    }
+
+    println!("Compression of {} images started!", paths.len());
+    let (tx, rx) = mpsc::channel();
+    let mut events = Vec::new();
+    thread::scope(|s| {
+        s.spawn(|| {
+            paths.par_iter().for_each_with(tx, |tx, path| {
+                tx.send(CompressionEvent {
+                    file: path.file_name().unwrap().to_string_lossy().to_string(),
+                    bytes_before: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                    bytes_after: compress_image(path, config, settings_modified),
+                })
+                .unwrap();
+            });
+        });
+
+        for (i, event) in rx.iter().enumerate() {
+            match &event.bytes_after {
+                Ok(Some(bytes_after)) => println!(
+                    "[{}/{}] {}: {} -> {} ({:.1}%)",
+                    i + 1,
+                    paths.len(),
+                    event.file,
+                    format_bytes(event.bytes_before),
+                    format_bytes(*bytes_after),
+                    ratio(event.bytes_before, *bytes_after)
+                ),
+                Ok(None) => println!("[{}/{}] {}: unchanged", i + 1, paths.len(), event.file),
+                Err(e) => println!("[{}/{}] {}: failed: {}", i + 1, paths.len(), event.file, e),
+            }
+            events.push(event);
+        }
+    });
+    println!("Compression of images finished!");
+
+    events.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(CompressionReport { events })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::site::CompressionOverride;
+
+    /// Empty folder of the temporary directory for the test called `name`.
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("personal-website-{}", name));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn compression_reports_every_image() {
+        let folder = temp_folder("compression");
+        let source = folder.join("source");
+        fs::create_dir_all(&source).unwrap();
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(64, 32, image::Rgb([146, 212, 171])));
+        img.save(source.join("a.png")).unwrap();
+        img.save(source.join("b.png")).unwrap();
+        img.save(source.join("c.png")).unwrap();
+        fs::write(source.join(".DS_Store"), "not an image").unwrap();
+
+        let mut config = CompressionConfig {
+            dest: folder.join("dest").to_string_lossy().to_string(),
+            ..CompressionConfig::default()
+        };
+        config.overrides.insert(
+            String::from("b.png"),
+            CompressionOverride {
+                quality: None,
+                size_ratio: Some(0.5),
+            },
+        );
+        config.overrides.insert(
+            String::from("c.png"),
+            CompressionOverride {
+                quality: Some(0.),
+                size_ratio: None,
+            },
+        );
+
+        let report = compress_images(source.to_str().unwrap(), &config, None).unwrap();
+        let files = report.events.iter().map(|e| e.file.as_str()).collect::<Vec<_>>();
+        assert_eq!(files, ["a.png", "b.png", "c.png"]);
+        assert!(matches!(report.events[0].bytes_after, Ok(Some(_))));
+        assert!(report.events[2].bytes_after.is_err());
+        assert!(report.has_errors());
+        let halved = image::open(Path::new(&config.dest).join("b.jpg")).unwrap();
+        assert_eq!((halved.width(), halved.height()), (32, 16));
+
+        // Outputs newer than their images are left as they are.
+        let report = compress_images(source.to_str().unwrap(), &config, None).unwrap();
+        assert!(matches!(report.events[0].bytes_after, Ok(None)));
+        assert!(matches!(report.events[1].bytes_after, Ok(None)));
+    }
+
+    #[test]
+    fn responsive_image_variants() {
+        let folder = temp_folder("responsive");
+        let source = folder.join("photo.png");
+        let dest = folder.join("variants").to_string_lossy().to_string();
+        let green = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1000, 10, image::Rgb([146, 212, 171])));
+        green.save(&source).unwrap();
+        let old = ResponsiveImage::from_file(source.to_str().unwrap(), &dest).unwrap();
+
+        // Another image whose name starts with the same one is left alone.
+        fs::write(Path::new(&dest).join("photo-2-000000000000-480.jpg"), "other").unwrap();
+
+        let grey = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1000, 10, image::Rgb([235, 233, 228])));
+        grey.save(&source).unwrap();
+        let image = ResponsiveImage::from_file(source.to_str().unwrap(), &dest).unwrap();
+
+        // Widths under the image's own one, then its own, in every format.
+        let variants = image.variants.iter().map(|v| (v.width, v.format)).collect::<Vec<_>>();
+        let mut expected = Vec::new();
+        for width in [480, 960, 1000] {
+            for format in ImageFormat::ALL {
+                expected.push((width, format));
+            }
+        }
+        assert_eq!(variants, expected);
+        assert!(image.paths().all(|path| Path::new(path).exists()));
+        assert!(image.variants[0].path.ends_with("-480.avif"));
+
+        // Variants of the older version are removed, with its placeholder.
+        assert!(old.paths().all(|path| !Path::new(path).exists()));
+        let mut files = fs::read_dir(&dest)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files.len(), 11);
+        assert_eq!(files.iter().filter(|f| f.ends_with("-placeholder.jpg")).count(), 1);
+        assert!(files.contains(&String::from("photo-2-000000000000-480.jpg")));
+
+        // A box 600 pixels wide takes the 960 variants, and the 1000 ones on
+        // screens of twice the density, the JPEG first for every browser.
+        let style = image.background_style(600);
+        let jpeg = format!("background-image:url(\"{}\")", image.jpeg_path(600));
+        assert!(style.contains(&jpeg));
+        assert!(image.jpeg_path(600).ends_with("-960.jpg"));
+        let (_, image_set) = style.split_once("image-set(").unwrap();
+        let (image_set, _) = image_set.split_once("), url(").unwrap();
+        let entries = image_set.split(", ").collect::<Vec<_>>();
+        assert_eq!(entries.len(), 6);
+        assert!(entries[0].ends_with("-960.avif\") type(\"image/avif\") 1x"));
+        assert!(entries[1].ends_with("-1000.avif\") type(\"image/avif\") 2x"));
+        assert!(entries[3].ends_with("-1000.webp\") type(\"image/webp\") 2x"));
+        assert!(entries[4].ends_with("-960.jpg\") type(\"image/jpeg\") 1x"));
+    }
+
+    #[test]
+    fn overlay_reports_every_image() {
+        let folder = temp_folder("overlay");
+        let source = folder.join("source");
+        fs::create_dir_all(&source).unwrap();
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([200, 100, 0])))
+            .save(source.join("a.png"))
+            .unwrap();
+        fs::write(source.join("b.png"), "not really an image").unwrap();
+        fs::write(source.join("notes.txt"), "Not an image.").unwrap();
+
+        let overlay = Overlay {
+            dest: folder.join("dest").to_string_lossy().to_string(),
+            color: String::from("#000000"),
+            opacity: 0.5,
+            blend: BlendMode::Normal,
+        };
+        let report = apply_overlay_to_images(source.to_str().unwrap(), &overlay, None).unwrap();
+        assert_eq!(report.done.len(), 1);
+        assert!(report.done[0].ends_with("a.png"));
+        assert!(report.not_images[0].ends_with("notes.txt"));
+        assert!(report.errors[0].0.ends_with("b.png"));
+        assert!(report.has_errors());
+
+        // Half of black over the image halves every channel.
+        let overlaid = image::open(Path::new(&overlay.dest).join("a.png")).unwrap().to_rgb8();
+        assert_eq!(overlaid.get_pixel(0, 0).0, [100, 50, 0]);
+
+        let report = apply_overlay_to_images(source.to_str().unwrap(), &overlay, None).unwrap();
+        assert!(report.done.is_empty());
+        assert_eq!(report.unchanged.len(), 1);
+
+        let bad_color = Overlay {
+            color: String::from("black"),
+            ..overlay
+        };
+        assert!(apply_overlay_to_images(source.to_str().unwrap(), &bad_color, None).is_err());
+    }
+
+    #[test]
+    fn overlay_blend_modes() {
+        assert_eq!(BlendMode::Normal.blend(0.2, 0.6), 0.6);
+        assert!((BlendMode::Multiply.blend(0.5, 0.5) - 0.25).abs() < 1e-6);
+        assert!((BlendMode::Screen.blend(0.5, 0.5) - 0.75).abs() < 1e-6);
+        assert!((BlendMode::Overlay.blend(0.25, 0.5) - 0.25).abs() < 1e-6);
+        assert!((BlendMode::Overlay.blend(0.75, 0.5) - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn placeholder_has_average_color() {
+        let folder = temp_folder("placeholder");
+        let source = folder.join("green.png");
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(64, 32, image::Rgb([146, 212, 171])))
+            .save(&source)
+            .unwrap();
+        let image = ResponsiveImage::from_file(source.to_str().unwrap(), folder.to_str().unwrap()).unwrap();
+
+        // JPEG shifts the color a little.
+        let rgb = hex_to_rgb(&image.color).unwrap();
+        for (channel, expected) in rgb.iter().zip([146, 212, 171]) {
+            assert!((*channel as i32 - expected).abs() <= 3, "{} isn't close to the image", image.color);
+        }
+        let (kind, data) = image.placeholder.split_once(',').unwrap();
+        assert_eq!(kind, "data:image/jpeg;base64");
+        let tiny = image::load_from_memory(&BASE64.decode(data).unwrap()).unwrap();
+        assert_eq!(tiny.width(), PLACEHOLDER_WIDTH);
+
+        assert_eq!(
+            image.placeholder_style(),
+            format!(
+                "background-color:{};background-image:url(\"{}\");",
+                image.color, image.placeholder
+            )
+        );
+        let mut script = String::new();
+        Backgrounds {
+            light: vec![image.clone()],
+            ..Backgrounds::default()
+        }
+        .write_placeholder_script(&mut script);
+        assert!(script.contains(&format!("var backgroundPlaceholders = [[\"{}\",\"{}\"]];", image.color, image.placeholder)));
+        assert!(script.contains("var darkBackgroundPlaceholders = [];"));
+    }
+
+    #[test]
+    fn backgrounds_from_overlay_source_with_credits() {
+        let folder = temp_folder("backgrounds");
+        let source = folder.join("img");
+        let light = folder.join("white-img");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&light).unwrap();
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(16, 8, image::Rgb([235, 233, 228])));
+        for name in ["beach.png", "blank.png", "forest.png"] {
+            img.save(source.join(name)).unwrap();
+        }
+        // Only the first two were overlaid, and an overlaid copy of an image
+        // that was removed from the source remains.
+        for name in ["beach.png", "blank.png", "removed.png"] {
+            img.save(light.join(name)).unwrap();
+        }
+
+        let credit = |photographer: &str| Credit {
+            photographer: photographer.to_string(),
+            link: None,
+            location: Some(String::from("Porto")),
+        };
+        let mut config = SiteConfig::default();
+        config.overlay.source = source.to_string_lossy().to_string();
+        config.overlay.light.dest = light.to_string_lossy().to_string();
+        config.backgrounds.exclude = vec![String::from("blank.png")];
+        config.backgrounds.credits.insert(String::from("beach.png"), credit("A"));
+        config.backgrounds.credits.insert(String::from("forest.png"), credit("B"));
+
+        let dest = folder.join("responsive-img").to_string_lossy().to_string();
+        let backgrounds = Backgrounds::from_config(&config, &dest).unwrap();
+        assert_eq!(backgrounds.light.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), ["beach"]);
+        assert!(backgrounds.dark.is_empty());
+        assert_eq!(backgrounds.credits.keys().collect::<Vec<_>>(), ["beach"]);
+
+        let script_path = folder.join("backgrounds.js");
+        backgrounds.write_script(script_path.to_str().unwrap()).unwrap();
+        let script = fs::read_to_string(&script_path).unwrap();
+        assert!(script.contains("var darkBackgroundImages = [];"));
+        assert!(script.contains(r#"var backgroundCredits = {"beach":{"photographer":"A","location":"Porto"}};"#));
+    }
+}
@@ -1,3 +1,20 @@
+mod about;
+mod chart;
+mod cv;
+mod gallery;
+mod highlight;
+mod icons;
+mod images;
+mod listing;
+mod offline;
+mod pdf;
+mod plotly;
+mod site;
+mod study;
+mod table;
+mod tags;
+
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::Write;
 use std::fs;
@@ -5,13 +22,7 @@ use std::fs::File;
 use std::io::Read;
 use std::io::Write as OtherWrite;
 use std::path::PathBuf;
-use std::sync::mpsc;
-
-use image_compressor::Factor;
-use image_compressor::FolderCompressor;
-
-use image::io::Reader as ImageReader;
-use image::{Pixel, Rgba};
+use std::process::Command;
 
 use chrono::NaiveDate;
 
@@ -19,117 +30,110 @@ use comrak::{markdown_to_html, ComrakOptions};
 
 use html_builder::*;
 
-const FOLDER_PUBLICATIONS: &str = "publications";
+use serde::Deserialize;
+
+use sha2::{Digest, Sha256};
+
+use about::build_who_am_i;
+use chart::{Chart, ChartSpec};
+use cv::{build_cv, Resume};
+use gallery::{build_gallery, Photo};
+use highlight::{Highlighter, FILE_HIGHLIGHT_CSS};
+use icons::{icon_path, make_icons, FILE_FAVICON, FILE_WEB_MANIFEST};
+use images::{apply_overlay_to_images, compress_images, Backgrounds, ResponsiveImage};
+use listing::Listing;
+use offline::{registration_script, write_service_worker, AssetManifest, FILE_ASSET_MANIFEST};
+use pdf::{blocks_from_html, PdfWriter, Span, Style};
+use plotly::{plot_arguments, PlotlyPlot};
+use site::SiteConfig;
+use study::{FigureRefs, Study};
+use table::{strip_tags, DataTable};
+use tags::{build_tag_pages, write_tag_chips, Taxonomy};
+
+const FILE_SITE_CONFIG: &str = "site.toml";
+const FILE_TAGS: &str = "tags.toml";
+const FILE_CV: &str = "cv.json";
+const FILE_ABOUT: &str = "about.md";
+const FILE_TIMELINE: &str = "timeline.toml";
+/// Checksums of the vendored Plotly runtimes, checked with `sha256sum -c`.
+const FILE_PLOTLY_CHECKSUMS: &str = "plotly.sha256";
 
-const NUMBERS: [&str; 2] = [
-    // "zero",
-    "one",
-    "two",
-    // "three",
-    // "four",
-    // "five",
-    // "six",
-    // "seven",
-    // "eight",
-    // "nine",
-    // "ten",
-    // "eleven",
-    // "twelve",
-    // "thirteen",
-    // "fourteen",
-    // "fifteen",
-    // "sixteen",
-    // "seventeen",
-    // "eighteen",
-    // "nineteen",
-];
+const FOLDER_PUBLICATIONS: &str = "publications";
+const FOLDER_SCRIPTS: &str = "js";
+const FOLDER_STUDIES: &str = "studies";
+const FOLDER_DATA: &str = "data";
+const FOLDER_FIGURES: &str = "figures";
+const FOLDER_RESPONSIVE_IMAGES: &str = "responsive-img";
 
 fn titlefy_to_page_title(title: &str) -> String {
-    return title
+    title
         .to_lowercase()
         .replace(|c: char| !c.is_ascii(), "")
         .replace(|c: char| c.is_ascii_whitespace(), "-")
-        .replace(|c: char| !(c.is_alphanumeric() || c == '-'), "");
+        .replace(|c: char| !(c.is_alphanumeric() || c == '-'), "")
 }
 
-fn apply_white_overlay_to_images(from_dest_dir: &str, to_dest_dir: &str) {
-    let dir_entries = fs::read_dir(from_dest_dir).unwrap();
-    fs::create_dir_all(to_dest_dir).unwrap();
-
-    for dir_entry in dir_entries {
-        let mut img = ImageReader::open(dir_entry.as_ref().unwrap().path())
-            .unwrap()
-            .decode()
-            .unwrap()
-            .to_rgba8();
-
-        for px in img.pixels_mut() {
-            px.blend(Rgba::from_slice(&[255, 255, 255, 229]));
-        }
-
-        println!(
-            "{}/{}",
-            to_dest_dir,
-            dir_entry
-                .as_ref()
-                .unwrap()
-                .file_name()
-                .into_string()
-                .unwrap(),
-        );
-
-        img.save(format!(
-            "{}/{}",
-            to_dest_dir,
-            dir_entry
-                .as_ref()
-                .unwrap()
-                .file_name()
-                .into_string()
-                .unwrap(),
-        ))
-        .unwrap();
-    }
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
 }
 
-fn compress_images(from_dest_dir: &str, to_dest_dir: &str) {
-    // Compress images in `img` folder for web
-    println!("Compression of images started!");
-    let origin = PathBuf::from(from_dest_dir); // original directory path
-    let dest = PathBuf::from(to_dest_dir); // destination directory path
-    let thread_count = 4; // number of threads
-    let (tx, _tr) = mpsc::channel(); // Sender and Receiver. for more info, check mpsc and message passing.
-
-    let mut comp = FolderCompressor::new(origin, dest);
-    comp.set_cal_func(|_width, _height, _file_size| return Factor::new(69., 1.0));
-    comp.set_thread_count(thread_count);
-    comp.set_sender(tx);
-
-    match comp.compress() {
-        Ok(_) => {}
-        Err(e) => println!("Cannot compress the folder!: {}", e),
-    }
+fn unescape_html(html: &str) -> String {
+    html.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
 
-    println!("Compression of images finished!");
+/// Struct that represents a button of the simple top bar, with the `label`
+/// shown on desktop, the `icon` shown on mobile, and the `link` it opens.
+struct TopBarButton<'a> {
+    label: &'a str,
+    icon: &'a str,
+    link: &'a str,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 enum CSS {
+    #[allow(dead_code)]
     Homemade,
     Science,
+    /// Same as `Science`, with the stylesheet that prints the CV.
+    CV,
 }
 
 struct Page {
-    title: String,
     css: CSS,
     buf: Buffer,
+    plotly: PlotlyRuntime,
+    data_table_script: bool,
 }
 
 impl Page {
-    fn new(title: &str, css: CSS) -> Self {
+    fn new(title: &str, css: CSS, config: &SiteConfig, backgrounds: &Backgrounds) -> Self {
+        Self::new_with_meta(title, css, config, backgrounds, &[], None)
+    }
+
+    /// Same as `new`, with extra `<meta>` properties, such as the Open Graph
+    /// ones that sharing previews are made from, and the `base` that relative
+    /// links start from, for pages that aren't published at the root.
+    fn new_with_meta(
+        title: &str,
+        css: CSS,
+        config: &SiteConfig,
+        backgrounds: &Backgrounds,
+        meta: &[(&str, String)],
+        base: Option<&str>,
+    ) -> Self {
         let mut page = Self {
-            title: title.to_string(),
-            css: css,
+            css,
             buf: Buffer::new(),
+            plotly: PlotlyRuntime::default(),
+            data_table_script: false,
         };
 
         // Header content
@@ -149,6 +153,11 @@ impl Page {
         // handled correctly.
         head.meta().attr("charset='utf-8'");
 
+        // Must come before any relative link.
+        if let Some(base) = base {
+            head.base().attr(format!("href='{}'", base).as_str());
+        }
+
         // For site responsiveness
         head.meta()
             .attr("name='viewport' content='width=device-width,initial-scale=1.0'");
@@ -156,11 +165,17 @@ impl Page {
         // Just like Buffer, nodes are also writable.  Set their contents by
         // writing into them.
         // Title
-        writeln!(head.title(), "Cláudio Gomes | {}", title).unwrap();
+        writeln!(head.title(), "Cláudio Gomes | {}", escape_html(title)).unwrap();
 
         // Description is the same as title.
         head.meta()
-            .attr(format!("name='description' content='{}'", title).as_str());
+            .attr(format!("name='description' content='{}'", escape_html(title)).as_str());
+
+        for (property, content) in meta {
+            head.meta().attr(
+                format!("property='{}' content='{}'", property, escape_html(content)).as_str(),
+            );
+        }
 
         // Necessary stylesheets.
         head.link()
@@ -179,8 +194,27 @@ impl Page {
 
         head.link().attr(match page.css {
             CSS::Homemade => "rel='stylesheet' href='css\\homemade.css'",
-            CSS::Science => "rel='stylesheet' href='css\\science.css'",
+            CSS::Science | CSS::CV => "rel='stylesheet' href='css\\science.css'",
         });
+        if let CSS::Science = page.css {
+            head.link()
+                .attr("rel='stylesheet' href='css\\highlight.css'");
+        }
+        head.link()
+            .attr("rel='stylesheet' media='print' href='css\\print.css'");
+        if let CSS::CV = page.css {
+            head.link()
+                .attr("rel='stylesheet' media='print' href='css\\cv-print.css'");
+        }
+
+        add_icon_links(&mut head, config);
+
+        if config.offline.enabled {
+            write!(head.script(), "{}", registration_script()).unwrap();
+        }
+
+        // Shows the placeholder of the background before the first paint.
+        backgrounds.write_placeholder_script(&mut head.script());
 
         html.script()
             .attr("src='https://cdn.jsdelivr.net/npm/sharer.js@latest/sharer.min.js'");
@@ -188,20 +222,54 @@ impl Page {
         html.script()
             .attr("src='https://kit.fontawesome.com/6a394e2d40.js' crossorigin='anonymous'");
 
+        html.script().attr("src='js/backgrounds.js'");
+
         let mut script = html.script();
         write!(script, "\
         function onLoad()
         {{
-            preload_image_object = new Image();
-            var imagesArray = ['carousel1.jpg', 'carousel2.jpg', 'carousel3.jpg', 'carousel4.jpg', 'carousel5.jpg', 'carousel6.jpg', 'carousel7.jpg', 'carousel8.jpg', 'carousel9.jpg', 'carousel10.jpg', 'carousel11.jpg', 'carousel12.jpg', 'carousel13.jpg', 'carousel14.jpg', 'carousel15.jpg', 'carousel16.jpg'];
-        
-            //Preload images for faster page response
-            for (var i=0; i < imagesArray.length; i++) {{
-                preload_image_object.src = imagesArray[i];
-                preload_image_object.onload = console.log(i);
-            }};
-            
-            document.getElementById('background-image-id').style.backgroundImage = 'url(\"./compressed-img/' + imagesArray[Math.floor(Math.random() * 16)] + '\")';
+            if (typeof backgroundImages === 'undefined') {{
+                return;
+            }}
+            var images = backgroundDark ? darkBackgroundImages : backgroundImages;
+            if (images.length === 0) {{
+                return;
+            }}
+
+            // Smallest variant of the background whose placeholder is shown,
+            // that covers the screen, in every format.
+            var image = images[backgroundIndex];
+            var needed = window.innerWidth * (window.devicePixelRatio || 1);
+            var widths = image.variants.map(function(v) {{ return v.width; }});
+            var width = widths.find(function(w) {{ return w >= needed; }}) || Math.max.apply(null, widths);
+            var variants = image.variants.filter(function(v) {{ return v.width === width; }});
+
+            // Browsers without image-set() ignore it and keep the JPEG. The
+            // placeholder stays underneath until the image has loaded.
+            var element = document.getElementById('background-image-id');
+            var placeholder = ', var(--background-placeholder, none)';
+            element.style.backgroundImage = 'url(\"./' + variants[variants.length - 1].path + '\")' + placeholder;
+            element.style.backgroundImage = 'image-set(' + variants.map(function(v) {{
+                return 'url(\"./' + v.path + '\") type(\"' + v.format + '\")';
+            }}).join(', ') + ')' + placeholder;
+
+            // Credit whoever took the photo, when they are known.
+            var credit = backgroundCredits[image.name];
+            var creditElement = document.getElementById('background-credit-id');
+            if (credit && creditElement) {{
+                var photographer = document.createElement(credit.link ? 'a' : 'span');
+                photographer.textContent = credit.photographer;
+                if (credit.link) {{
+                    photographer.href = credit.link;
+                    photographer.rel = 'noopener';
+                }}
+                creditElement.textContent = 'Photo by ';
+                creditElement.appendChild(photographer);
+                if (credit.location) {{
+                    creditElement.appendChild(document.createTextNode(', ' + credit.location));
+                }}
+                creditElement.hidden = false;
+            }}
         }}
 
         let lastScrollTop = 0;
@@ -221,17 +289,21 @@ impl Page {
         let mut body = html
             .body()
             .attr("class='gallery-background' id='background-image-id' onload='onLoad()'");
+        // Credit of the background, filled in once it is picked
+        body.div()
+            .attr("class='background-credit' id='background-credit-id' hidden");
         // Container to apply shadow
-        let shadow_gradient = body.div().attr("class='special-shadow-gradient'");
+        body.div().attr("class='special-shadow-gradient'");
 
         page
     }
 
-    fn new_without_background(title: &str, css: CSS) -> Self {
+    fn new_without_background(title: &str, css: CSS, config: &SiteConfig) -> Self {
         let mut page = Self {
-            title: title.to_string(),
-            css: css,
+            css,
             buf: Buffer::new(),
+            plotly: PlotlyRuntime::default(),
+            data_table_script: false,
         };
 
         // Header content
@@ -258,11 +330,11 @@ impl Page {
         // Just like Buffer, nodes are also writable.  Set their contents by
         // writing into them.
         // Title
-        writeln!(head.title(), "Cláudio Gomes | {}", title).unwrap();
+        writeln!(head.title(), "Cláudio Gomes | {}", escape_html(title)).unwrap();
 
         // Description is the same as title.
         head.meta()
-            .attr(format!("name='description' content='{}'", title).as_str());
+            .attr(format!("name='description' content='{}'", escape_html(title)).as_str());
 
         // Necessary stylesheets.
         head.link()
@@ -281,8 +353,24 @@ impl Page {
 
         head.link().attr(match page.css {
             CSS::Homemade => "rel='stylesheet' href='css\\homemade.css'",
-            CSS::Science => "rel='stylesheet' href='css\\science.css'",
+            CSS::Science | CSS::CV => "rel='stylesheet' href='css\\science.css'",
         });
+        if let CSS::Science = page.css {
+            head.link()
+                .attr("rel='stylesheet' href='css\\highlight.css'");
+        }
+        head.link()
+            .attr("rel='stylesheet' media='print' href='css\\print.css'");
+        if let CSS::CV = page.css {
+            head.link()
+                .attr("rel='stylesheet' media='print' href='css\\cv-print.css'");
+        }
+
+        add_icon_links(&mut head, config);
+
+        if config.offline.enabled {
+            write!(head.script(), "{}", registration_script()).unwrap();
+        }
 
         html.script()
             .attr("src='https://cdn.jsdelivr.net/npm/sharer.js@latest/sharer.min.js'");
@@ -292,15 +380,15 @@ impl Page {
 
 
         // Body
-        let mut body = html
-            .body();
+        html.body();
         // Container to apply shadow
         page
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn add_top_bar(
         &mut self,
-        path_img: &str,
+        profile_pic: &ResponsiveImage,
         page_a: &str,
         page_b: &str,
         page_c: &str,
@@ -317,46 +405,54 @@ impl Page {
             .div()
             .attr("class='columns col-gapless full-height ultra-wide-treatment'");
         let mut column_a = columns.div().attr("class='column col-2-and-half'");
-        let mut button_a = column_a.button().attr("class='btn btn-top-bar'");
-        write!(button_a, "{}", page_a);
+        let mut button_a = column_a
+            .button()
+            .attr(format!("class='btn btn-top-bar'{}", aria_current(page_a, active_page)).as_ref());
+        write!(button_a, "{}", page_a).unwrap();
         let mut column_b = columns.div().attr("class='column col-2-and-half'");
-        let mut button_b = column_b.button().attr("class='btn btn-top-bar'");
-        write!(button_b, "{}", page_b);
+        let mut button_b = column_b
+            .button()
+            .attr(format!("class='btn btn-top-bar'{}", aria_current(page_b, active_page)).as_ref());
+        write!(button_b, "{}", page_b).unwrap();
 
         let mut column_pic = columns.div().attr("class='column col-2'");
         let mut parallax_pic = column_pic
             .div()
             .attr("class='parallax square-pic-parallax'");
-        let mut parallax_top_left = parallax_pic
+        parallax_pic
             .div()
             .attr("class='parallax-top-left' tabindex='1'");
-        let mut parallax_top_right = parallax_pic
+        parallax_pic
             .div()
             .attr("class='parallax-top-right' tabindex='2'");
-        let mut parallax_bottom_left = parallax_pic
+        parallax_pic
             .div()
             .attr("class='parallax-bottom-left' tabindex='3'");
-        let mut parallax_bottom_right = parallax_pic
+        parallax_pic
             .div()
             .attr("class='parallax-bottom-right' tabindex='4'");
         let mut parallax_content = parallax_pic.div().attr("class='parallax-content'");
-        let mut parallax_front = parallax_content.div().attr("class='parallax-front'");
+        parallax_content.div().attr("class='parallax-front'");
         let mut parallax_back = parallax_content.div().attr("class='parallax-back'");
         parallax_back.div().attr(
             format!(
-                "style='background-image:url(\"{}\")' class='square-pic-img'",
-                path_img
+                "style='{}' class='square-pic-img'",
+                profile_pic.background_style(220)
             )
             .as_ref(),
         );
 
         let mut column_c = columns.div().attr("class='column col-2-and-half'");
-        let mut button_c = column_c.button().attr("class='btn btn-top-bar'");
-        write!(button_c, "{}", page_c);
+        let mut button_c = column_c
+            .button()
+            .attr(format!("class='btn btn-top-bar'{}", aria_current(page_c, active_page)).as_ref());
+        write!(button_c, "{}", page_c).unwrap();
 
         let mut column_d = columns.div().attr("class='column col-2-and-half'");
-        let mut button_d = column_d.button().attr("class='btn btn-top-bar'");
-        write!(button_d, "{}", page_d);
+        let mut button_d = column_d
+            .button()
+            .attr(format!("class='btn btn-top-bar'{}", aria_current(page_d, active_page)).as_ref());
+        write!(button_d, "{}", page_d).unwrap();
 
         // Mobile top bar
         let mut container = self.buf.div().attr("class='top-bar-mobile show-xl'");
@@ -364,33 +460,53 @@ impl Page {
             .div()
             .attr("class='columns col-gapless full-height'");
         let mut column_a = columns.div().attr("class='column col-2-and-quarter'");
-        let mut button_a = column_a
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_a).as_ref());
+        column_a.button().attr(
+            format!(
+                "class='btn btn-top-bar-mobile fa-solid {}'{}",
+                icon_a,
+                aria_current(page_a, active_page)
+            )
+            .as_ref(),
+        );
 
         let mut column_b = columns.div().attr("class='column col-2-and-quarter'");
-        let mut button_b = column_b
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_b).as_ref());
+        column_b.button().attr(
+            format!(
+                "class='btn btn-top-bar-mobile fa-solid {}'{}",
+                icon_b,
+                aria_current(page_b, active_page)
+            )
+            .as_ref(),
+        );
 
         let mut column_home = columns.div().attr("class='column col-3'");
         column_home.button().attr(
             format!(
-                "style='background-image:url(\"{}\")' class='btn btn-home-top-bar-mobile'",
-                path_img
+                "style='{}' class='btn btn-home-top-bar-mobile'",
+                profile_pic.background_style(80)
             )
             .as_ref(),
         );
 
         let mut column_c = columns.div().attr("class='column col-2-and-quarter'");
-        let mut button_c = column_c
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_c).as_ref());
+        column_c.button().attr(
+            format!(
+                "class='btn btn-top-bar-mobile fa-solid {}'{}",
+                icon_c,
+                aria_current(page_c, active_page)
+            )
+            .as_ref(),
+        );
 
         let mut column_d = columns.div().attr("class='column col-2-and-quarter'");
-        let mut button_d = column_d
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_d).as_ref());
+        column_d.button().attr(
+            format!(
+                "class='btn btn-top-bar-mobile fa-solid {}'{}",
+                icon_d,
+                aria_current(page_d, active_page)
+            )
+            .as_ref(),
+        );
 
         self.buf.div().attr("class='top-bar-invisible hide-xl'");
         self.buf
@@ -398,91 +514,68 @@ impl Page {
             .attr("class='top-bar-mobile-invisible show-xl'");
     }
     
-    fn add_simple_top_bar(
-        &mut self,
-        page_a: &str,
-        page_b: &str,
-        page_c: &str,
-        page_d: &str,
-        icon_a: &str,
-        icon_b: &str,
-        icon_c: &str,
-        icon_d: &str,
-        active_page: Option<&str>,
-        link_a: Option<&str>,
-        link_b: Option<&str>,
-        link_c: Option<&str>,
-        link_d: Option<&str>,
-    ) {
+    /// Adds the top bar every page of the site shares, with `active_page`
+    /// marked as the current one.
+    fn add_site_top_bar(&mut self, profile_pic: &ResponsiveImage, active_page: Option<&str>) {
+        self.add_top_bar(
+            profile_pic,
+            "Who am I?",
+            "Publications",
+            "Miscellaneous",
+            "CV",
+            "fa-person-rays",
+            "fa-atom",
+            "fa-cow",
+            "fa-address-book",
+            active_page,
+        );
+    }
+
+    fn add_simple_top_bar(&mut self, buttons: &[TopBarButton], active_page: Option<&str>) {
+        // Buttons share the 12 columns of the grid evenly.
+        let column_class = format!("column col-{}", 12 / buttons.len().max(1));
+
         // Desktop top bar
         let mut container = self.buf.div().attr("class='top-bar hide-xl'");
         let mut columns = container
             .div()
             .attr("class='columns col-gapless full-height ultra-wide-treatment'");
-        let mut column_a = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_a {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_a = column_a.button().attr("class='btn btn-top-bar'");
-        write!(button_a, "{}", page_a);
-        
-        let mut column_b = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_b {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_b = column_b.button().attr("class='btn btn-top-bar'");
-        write!(button_b, "{}", page_b);
-
-        let mut column_c = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_c {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_c = column_c.button().attr("class='btn btn-top-bar'");
-        write!(button_c, "{}", page_c);
-
-        let mut column_d = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_d {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_d = column_d.button().attr("class='btn btn-top-bar'");
-        write!(button_d, "{}", page_d);
+        for button in buttons {
+            let mut column = columns.div().attr(
+                format!(
+                    "class='{}' onclick='window.location.href=\"{}\";'",
+                    column_class, button.link
+                )
+                .as_ref(),
+            );
+            let mut button_node = column
+                .button()
+                .attr(format!("class='btn btn-top-bar'{}", aria_current(button.label, active_page)).as_ref());
+            write!(button_node, "{}", button.label).unwrap();
+        }
 
         // Mobile top bar
         let mut container = self.buf.div().attr("class='top-bar-mobile show-xl'");
         let mut columns = container
             .div()
             .attr("class='columns col-gapless full-height'");
-        let mut column_a = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_a {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_a = column_a
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_a).as_ref());
-
-        let mut column_b = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_b {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_b = column_b
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_b).as_ref());
-
-        let mut column_c = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_c {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_c = column_c
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_c).as_ref());
-
-        let mut column_d = columns.div().attr(format!("class='column col-3' onclick='window.location.href=\"{}\";'", match link_d {
-            Some(link) => link,
-            _ => ""
-        }).as_ref());
-        let mut button_d = column_d
-            .button()
-            .attr(format!("class='btn btn-top-bar-mobile fa-solid {}'", icon_d).as_ref());
+        for button in buttons {
+            let mut column = columns.div().attr(
+                format!(
+                    "class='{}' onclick='window.location.href=\"{}\";'",
+                    column_class, button.link
+                )
+                .as_ref(),
+            );
+            column.button().attr(
+                format!(
+                    "class='btn btn-top-bar-mobile fa-solid {}'{}",
+                    button.icon,
+                    aria_current(button.label, active_page)
+                )
+                .as_ref(),
+            );
+        }
 
         self.buf.div().attr("class='top-bar-invisible hide-xl'");
         self.buf
@@ -490,7 +583,7 @@ impl Page {
             .attr("class='top-bar-mobile-invisible show-xl'");
     }
 
-    fn add_columns(&mut self, classes: &str) -> Node {
+    fn add_columns(&mut self, classes: &str) -> Node<'_> {
         let columns = self.buf.div().attr(
             format!(
                 "class='columns page-height page-ultra-wide-treatment {}'",
@@ -501,13 +594,6 @@ impl Page {
         columns
     }
 
-    fn add_figure(&mut self, figure_path: &str) {
-        let mut figure_file = File::open(figure_path).unwrap();
-        let mut figure_str = String::new();
-        figure_file.read_to_string(&mut figure_str).unwrap();
-        write!(self.buf, "{}", figure_str).unwrap();
-    }
-
     fn add_footer(&mut self) {
         self.buf.footer();
     }
@@ -518,37 +604,552 @@ impl Page {
     }
 }
 
+// Add favicon, touch icon and web app manifest links to head
+fn add_icon_links(head: &mut Node, config: &SiteConfig) {
+    head.link()
+        .attr(format!("rel='icon' href='{}' sizes='any'", FILE_FAVICON).as_str());
+    head.link().attr(
+        format!("rel='icon' type='image/png' sizes='32x32' href='{}'", icon_path("icon-32")).as_str(),
+    );
+    head.link()
+        .attr(format!("rel='apple-touch-icon' href='{}'", icon_path("apple-touch-icon")).as_str());
+    head.link()
+        .attr(format!("rel='manifest' href='{}'", FILE_WEB_MANIFEST).as_str());
+    head.meta().attr(
+        format!("name='theme-color' content='{}'", escape_html(&config.manifest.theme_color)).as_str(),
+    );
+}
+
+/// Attribute that marks the button of `page` as the current page, when it's
+/// `active_page`.
+fn aria_current(page: &str, active_page: Option<&str>) -> &'static str {
+    match active_page == Some(page) {
+        true => " aria-current='page'",
+        false => "",
+    }
+}
+
 // Add responsive column to columns div
 fn add_column_to_dual_columns<'a>(columns: &'a mut Node) -> Node<'a> {
-    let mut col = columns.div().attr("class='column col-6 col-xl-12'");
+    let col = columns.div().attr("class='column col-6 col-xl-12'");
 
     col
 }
 
-fn add_figure_to_node(node: &mut Node, figure_path: &str){
-    let mut figure_file = File::open(figure_path).unwrap();
-    let mut figure_str = String::new();
-    figure_file.read_to_string(&mut figure_str).unwrap();
-    write!(node, "{}", figure_str).unwrap();
+/// Adds the card of `post` that the homepage shows, with its `excerpt`, and
+/// its `thumbnail` beside them if it has one.
+fn add_post_card(
+    node: &mut Node,
+    post: &Publication,
+    thumbnail: Option<&ResponsiveImage>,
+    excerpt: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut card = node.a().attr(
+        format!(
+            "class='article_container{}' href='{}'",
+            match thumbnail {
+                Some(_) => " misc_container",
+                None => "",
+            },
+            post.page_path()
+        )
+        .as_str(),
+    );
+    let mut text = match thumbnail {
+        Some(thumbnail) => {
+            thumbnail.write_picture(&mut card, "", "160px", "misc_thumbnail");
+            card.div().attr("class='misc_text'")
+        }
+        None => card,
+    };
+    write!(
+        text.h4().attr("class='no_margin'").b(),
+        "{}",
+        escape_html(&post.title)
+    )?;
+    write!(text.h5().attr("class='date_color no_margin'"), "{}", post.date)?;
+    write!(
+        text.p().attr("class='auto_crop no_margin'"),
+        "{}",
+        escape_html(excerpt)
+    )?;
+
+    Ok(())
+}
+
+/// Adds a Plotly figure with its static version, description and data,
+/// returning the paths of the data files written for it.
+fn add_figure_to_node(
+    w: &mut dyn Write,
+    plotly: &mut PlotlyRuntime,
+    figure_path: &str,
+    loading: FigureLoading,
+) -> Result<Vec<String>, &'static str> {
+    let mut figure = Figure::from_file(figure_path)?;
+    let figure_name = PathBuf::from(figure_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| figure.id.clone());
+
+    // Figures whose data can't be read or converted only get their
+    // interactive version, so they never fail the build.
+    let plot = match PlotlyPlot::from_data_script(&figure.data_script) {
+        Ok(p) => Some(p),
+        Err(e) => {
+            println!("Couldn't read data of figure {:?}: {}", figure_path, e);
+            None
+        }
+    };
+    let chart = match plot.as_ref().map(|p| p.to_chart()) {
+        Some(Ok(c)) => Some(c),
+        Some(Err(e)) => {
+            println!("No static version of figure {:?}: {}", figure_path, e);
+            None
+        }
+        None => None,
+    };
+    if let Some(chart) = &chart {
+        figure.graph_div = figure.graph_div.replacen(
+            "<div ",
+            &format!("<div {} ", chart_aria_attributes(&figure.id, chart)),
+            1,
+        );
+    }
+
+    let svg_path = match &chart {
+        Some(chart) => Some(write_static_figure(w, &figure_name, chart)?),
+        None => None,
+    };
+    plotly.write_once(w, &figure)?;
+    match loading {
+        FigureLoading::Eager => write!(w, "{}", figure).unwrap(),
+        FigureLoading::Lazy => {
+            plotly.write_lazy_loader_once(w);
+            let preview = svg_path.as_deref().zip(chart.as_ref().map(|c| c.label()));
+            figure.write_lazy(w, preview)?;
+        }
+    }
+
+    if let Some(chart) = &chart {
+        write_chart_description(w, &figure.id, chart);
+    }
+    match &plot {
+        Some(plot) => write_figure_data(w, &figure_name, &plot.to_data_table()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Writes the SVG of a Plotly figure to `FOLDER_FIGURES`, for readers without
+/// JavaScript and for print, and returns its path.
+fn write_static_figure(w: &mut dyn Write, figure_name: &str, chart: &Chart) -> Result<String, &'static str> {
+    let svg = chart.to_svg();
+    let svg_path = format!("{}/{}.svg", FOLDER_FIGURES, figure_name);
+    match fs::create_dir_all(FOLDER_FIGURES).and_then(|_| fs::write(&svg_path, &svg)) {
+        Ok(_) => (),
+        _ => return Err("Couldn't write static figure file."),
+    };
+
+    // The image is hidden on screen, and shown by the print stylesheet and by
+    // the style the Plotly runtime writes for readers without JavaScript;
+    // lazy loading keeps browsers from downloading it until then.
+    write!(
+        w,
+        "<img class='figure-static' src='{}' loading='lazy' alt='{}'>",
+        svg_path,
+        escape_html(&chart.label())
+    )
+    .unwrap();
+    Ok(svg_path)
+}
+
+/// Renders a chart declared in a manifest to SVG, which is both written to
+/// `FOLDER_FIGURES` and inlined, so that it needs no JavaScript. Returns the
+/// paths of the data files written for it.
+fn add_chart_to_node(w: &mut dyn Write, chart_id: &str, spec: &ChartSpec) -> Result<Vec<String>, &'static str> {
+    let chart = Chart::from_spec(spec)?;
+    let svg = chart.to_svg();
+
+    match fs::create_dir_all(FOLDER_FIGURES)
+        .and_then(|_| fs::write(format!("{}/{}.svg", FOLDER_FIGURES, chart_id), &svg))
+    {
+        Ok(_) => (),
+        _ => return Err("Couldn't write chart file."),
+    };
+
+    write!(
+        w,
+        "<div class='figure-svg' {}>{}</div>",
+        chart_aria_attributes(chart_id, &chart),
+        svg
+    )
+    .unwrap();
+    write_chart_description(w, chart_id, &chart);
+    write_figure_data(w, chart_id, &DataTable::from_file(&spec.data)?)
+}
+
+/// Writes the data of a figure to `FOLDER_DATA` as CSV and JSON, with links
+/// to download them, and returns the paths of both files.
+fn write_figure_data(w: &mut dyn Write, figure_name: &str, table: &DataTable) -> Result<Vec<String>, &'static str> {
+    let csv_path = format!("{}/{}.csv", FOLDER_DATA, figure_name);
+    let json_path = format!("{}/{}.json", FOLDER_DATA, figure_name);
+    match fs::create_dir_all(FOLDER_DATA)
+        .and_then(|_| fs::write(&csv_path, table.to_csv()))
+        .and_then(|_| fs::write(&json_path, table.to_json()))
+    {
+        Ok(_) => (),
+        _ => return Err("Couldn't write figure data files."),
+    };
+
+    write!(
+        w,
+        "<div class='figure-downloads'>\
+        <a class='btn btn-link' href='{}' download><i class='fa-solid fa-download'></i> Download CSV</a>\
+        <a class='btn btn-link' href='{}' download><i class='fa-solid fa-download'></i> Download JSON</a>\
+        </div>",
+        csv_path, json_path
+    )
+    .unwrap();
+    Ok(vec![csv_path, json_path])
+}
+
+/// ARIA attributes that name a chart and point to its description and data,
+/// which are written by `write_chart_description`.
+fn chart_aria_attributes(chart_id: &str, chart: &Chart) -> String {
+    format!(
+        "role='img' aria-label='{}' aria-describedby='{id}-summary' aria-details='{id}-data'",
+        escape_html(&chart.label()),
+        id = chart_id
+    )
+}
+
+/// Writes a collapsible text summary of a chart and the data it is drawn
+/// from, for readers who can't see it.
+fn write_chart_description(w: &mut dyn Write, chart_id: &str, chart: &Chart) {
+    let label = escape_html(&chart.label());
+    write!(
+        w,
+        "<details class='figure-description'>\
+        <summary>Description and data of the chart</summary>\
+        <p id='{id}-summary'>{}. {}</p>",
+        label,
+        escape_html(&chart.summary()),
+        id = chart_id
+    )
+    .unwrap();
+    chart
+        .to_data_table()
+        .write_plain(w, &format!("{}-data", chart_id), &label);
+    write!(w, "</details>").unwrap();
+}
+
+/// Enum to enumerate the two ways of loading a figure:
+/// `Eager` renders the chart as soon as the page loads;
+/// `Lazy` renders a lightweight placeholder, and defers the chart until the
+/// placeholder scrolls into view or the reader clicks to load it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FigureLoading {
+    #[default]
+    Eager,
+    Lazy,
+}
+
+/// Struct that represents a figure exported by Plotly, split into the
+/// runtime and config shared by every figure (`plotly_version` and
+/// `plotly_config`) and the parts that are unique to it (`graph_div` and
+/// `data_script`).
+#[derive(Debug)]
+struct Figure {
+    id: String,
+    plotly_version: String,
+    plotly_config: String,
+    graph_div: String,
+    data_script: String,
+}
+
+impl Figure {
+    fn from_file(figure_path: &str) -> Result<Self, &'static str> {
+        let mut figure_file = match File::open(figure_path) {
+            Ok(f) => f,
+            _ => return Err("Couldn't open figure file."),
+        };
+        let mut figure_str = String::new();
+        match figure_file.read_to_string(&mut figure_str) {
+            Ok(_) => (),
+            _ => return Err("Couldn't read figure contents."),
+        };
+
+        Self::from_html(&figure_str)
+    }
+
+    fn from_html(figure_str: &str) -> Result<Self, &'static str> {
+        let mut plotly_version = None;
+        let mut plotly_config = None;
+        let mut data_script = None;
+
+        // Sort every <script> of the figure into runtime, config or data.
+        let mut rest = figure_str;
+        while let Some(start) = rest.find("<script") {
+            let script = &rest[start..];
+            let (tag, script) = match script.split_once('>') {
+                Some(s) => s,
+                _ => return Err("Couldn't find end of script tag."),
+            };
+            let (content, after) = match script.split_once("</script>") {
+                Some(s) => s,
+                _ => return Err("Couldn't find closing script tag."),
+            };
+
+            if let Some((_, src)) = tag.split_once("plotly-") {
+                if let Some((version, _)) = src.split_once(".min.js") {
+                    plotly_version = Some(version.to_string());
+                }
+            } else if content.contains("window.PlotlyConfig") {
+                plotly_config = Some(content.trim().to_string());
+            } else if content.contains("Plotly.newPlot") {
+                data_script = Some(content.trim().to_string());
+            }
+
+            rest = after;
+        }
+
+        // The graph div is empty, so it ends at the first closing div.
+        let graph_div = match figure_str.find("<div id=") {
+            Some(start) => match figure_str[start..].find("</div>") {
+                Some(end) => &figure_str[start..start + end + "</div>".len()],
+                _ => return Err("Couldn't find end of graph div."),
+            },
+            _ => return Err("Couldn't find graph div."),
+        };
+
+        let id = match graph_div
+            .split_once("id=\"")
+            .and_then(|(_, id)| id.split_once('"'))
+        {
+            Some((id, _)) => id,
+            _ => return Err("Couldn't find id of graph div."),
+        };
+
+        Ok(Self {
+            id: id.to_string(),
+            plotly_version: match plotly_version {
+                Some(v) => v,
+                _ => return Err("Couldn't find Plotly runtime."),
+            },
+            plotly_config: plotly_config.unwrap_or_default(),
+            graph_div: graph_div.to_string(),
+            data_script: match data_script {
+                Some(d) => d,
+                _ => return Err("Couldn't find Plotly data script."),
+            },
+        })
+    }
+}
+
+impl Figure {
+    /// Arguments of the `Plotly.newPlot` call of the data script, as a JSON
+    /// array: the id of the div, the traces, the layout and the config.
+    fn plot_arguments(&self) -> Result<String, &'static str> {
+        let values = plot_arguments(&self.data_script)?;
+        // The JSON sits in a script, which "</" would end.
+        Ok(serde_json::Value::Array(values).to_string().replace("</", "<\\/"))
+    }
+
+    /// Writes a placeholder that shows `preview`, the path and description
+    /// of the static version of the chart, if there is one, with the chart
+    /// hidden inside. Its data is only plotted by `loadFigure` (see
+    /// `PlotlyRuntime::write_lazy_loader_once`).
+    fn write_lazy(&self, w: &mut dyn Write, preview: Option<(&str, String)>) -> Result<(), &'static str> {
+        let preview = match preview {
+            Some((svg_path, label)) => format!(
+                "<img class='figure-preview-img' src='{}' loading='lazy' alt='{}'>",
+                svg_path,
+                escape_html(&label)
+            ),
+            None => String::from("<i class='fa-solid fa-chart-column figure-preview-icon'></i>"),
+        };
+        write!(
+            w,
+            "<div class='figure-lazy' data-figure-id='{id}'>\
+            <div class='figure-preview'>{}\
+            <button class='btn btn-primary' onclick='loadFigure(\"{id}\")'>Load interactive chart</button>\
+            </div>\
+            {}<script type='application/json' class='figure-data'>{}</script></div>",
+            preview,
+            self.graph_div,
+            self.plot_arguments()?,
+            id = self.id,
+        )
+        .unwrap();
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Figure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "<div>{}<script type='text/javascript'>{}</script></div>",
+            self.graph_div, self.data_script
+        )
+    }
+}
+
+/// Keeps track of the Plotly runtime written to a page, so that the runtime
+/// and its config are written only once, before the first figure, and every
+/// other figure of the page targets the same Plotly version.
+#[derive(Debug, Default)]
+struct PlotlyRuntime {
+    version: Option<String>,
+    lazy_loader: bool,
+}
+
+impl PlotlyRuntime {
+    fn write_once(&mut self, w: &mut dyn Write, figure: &Figure) -> Result<(), &'static str> {
+        match &self.version {
+            Some(version) if *version == figure.plotly_version => Ok(()),
+            Some(_) => Err("Figures of the same page target different Plotly versions."),
+            None => {
+                // The runtime is self-hosted; `vendor_plotly_runtime` makes
+                // sure it's there.
+                let src = plotly_runtime_path(&figure.plotly_version);
+                write!(
+                    w,
+                    "<script type='text/javascript'>{}</script><script src='{}'></script>\
+                    <noscript><style>.figure-static {{ display: block; }} .figure-preview {{ display: none; }}</style></noscript>",
+                    figure.plotly_config, src
+                )
+                .unwrap();
+
+                self.version = Some(figure.plotly_version.clone());
+                Ok(())
+            }
+        }
+    }
+
+    fn write_lazy_loader_once(&mut self, w: &mut dyn Write) {
+        if self.lazy_loader {
+            return;
+        }
+
+        // Runs the data script of a lazy figure once, either when the reader
+        // clicks its button or when it gets close to the viewport.
+        write!(w, "<script type='text/javascript'>
+        function loadFigure(id)
+        {{
+            var container = document.querySelector('.figure-lazy[data-figure-id=\"' + id + '\"]');
+            if (!container || container.classList.contains('figure-loaded')) {{
+                return;
+            }}
+            container.classList.add('figure-loaded');
+
+            var plot = JSON.parse(container.querySelector('.figure-data').textContent);
+            Plotly.newPlot.apply(Plotly, plot);
+        }}
+
+        if ('IntersectionObserver' in window) {{
+            let figureObserver = new IntersectionObserver(function(entries) {{
+                entries.forEach(function(entry) {{
+                    if (entry.isIntersecting) {{
+                        figureObserver.unobserve(entry.target);
+                        loadFigure(entry.target.dataset.figureId);
+                    }}
+                }});
+            }}, {{ rootMargin: '200px' }});
+
+            window.addEventListener('DOMContentLoaded', function() {{
+                document.querySelectorAll('.figure-lazy').forEach(function(container) {{
+                    figureObserver.observe(container);
+                }});
+            }});
+        }}
+        </script>").unwrap();
+
+        self.lazy_loader = true;
+    }
+}
+
+fn plotly_runtime_path(version: &str) -> String {
+    format!("{}/plotly-{}.min.js", FOLDER_SCRIPTS, version)
+}
+
+/// Downloads the Plotly runtime of `version` to `FOLDER_SCRIPTS`, if it isn't
+/// there yet, and checks it against its checksum in `FILE_PLOTLY_CHECKSUMS`.
+/// A runtime without a checksum gets one added to the file, which is then
+/// to be committed so that later downloads are checked against it.
+fn vendor_plotly_runtime(version: &str) -> Result<(), &'static str> {
+    let path = plotly_runtime_path(version);
+    if !PathBuf::from(&path).exists() {
+        let url = format!("https://cdn.plot.ly/plotly-{}.min.js", version);
+        println!("Downloading Plotly runtime {:?}", url);
+        if fs::create_dir_all(FOLDER_SCRIPTS).is_err() {
+            return Err("Couldn't create folder.");
+        }
+        let downloaded = Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location", "--output", &path, &url])
+            .status();
+        if !matches!(downloaded, Ok(status) if status.success()) {
+            let _ = fs::remove_file(&path);
+            println!("Download {:?} to {:?}.", url, path);
+            return Err("Couldn't download the Plotly runtime.");
+        }
+    }
+
+    let runtime = match fs::read(&path) {
+        Ok(r) => r,
+        _ => return Err("Couldn't read the Plotly runtime."),
+    };
+    let banner = format!("plotly.js v{}", version);
+    if !String::from_utf8_lossy(&runtime[..runtime.len().min(512)]).contains(&banner) {
+        return Err("Couldn't find the version banner of the Plotly runtime.");
+    }
+    let checksum = format!("{:x}", Sha256::digest(&runtime));
+
+    let checksums = fs::read_to_string(FILE_PLOTLY_CHECKSUMS).unwrap_or_default();
+    let pinned = checksums
+        .lines()
+        .filter_map(|l| l.split_once("  "))
+        .find(|(_, p)| *p == path)
+        .map(|(c, _)| c);
+    match pinned {
+        Some(pinned) if pinned == checksum => Ok(()),
+        Some(_) => {
+            println!("{:?} doesn't match its checksum in {:?}.", path, FILE_PLOTLY_CHECKSUMS);
+            Err("Couldn't verify the Plotly runtime.")
+        }
+        None => {
+            println!("Pinning {:?} in {:?}", path, FILE_PLOTLY_CHECKSUMS);
+            match fs::write(FILE_PLOTLY_CHECKSUMS, format!("{}{}  {}\n", checksums, checksum, path)) {
+                Ok(_) => Ok(()),
+                _ => Err("Couldn't write Plotly checksums."),
+            }
+        }
+    }
 }
 
 /// Struct that represents a publication, which contains info such
-/// as title, date, markdown, and tags.
+/// as title, date, markdown, and tags, and the thumbnail and abstract the
+/// gobbet declares, if any.
 #[derive(Debug)]
 struct Publication {
     title: String,
     date: NaiveDate,
     markdown: String,
     tags: Vec<String>,
+    thumbnail: Option<String>,
+    summary: Option<String>,
 }
 
 impl Publication {
+    #[cfg(test)]
     fn new(title: &str, date: NaiveDate, markdown: &str, tags: Vec<String>) -> Self {
         Self {
             title: title.to_string(),
-            date: date,
+            date,
             markdown: markdown.to_string(),
-            tags: tags,
+            tags,
+            thumbnail: None,
+            summary: None,
         }
     }
 
@@ -568,7 +1169,7 @@ impl Publication {
             }
         }
 
-        posts.sort_by(|a, b| b.date.cmp(&a.date));
+        posts.sort_by_key(|p| std::cmp::Reverse(p.date));
 
         Ok(posts)
     }
@@ -600,9 +1201,23 @@ impl Publication {
                 if let Some((markdown, tags)) = gobbet_contents.split_once("🍖TAGS🍖") {
                     let markdown = &markdown.replace("🍖MARKDOWN🍖", "");
                     let markdown = markdown.trim();
-                    return Ok(Self {
+                    // Thumbnail and abstract, which are optional and follow
+                    // the tags in any order
+                    let mut sections = tags.split('🍖');
+                    let tags = sections.next().unwrap_or("");
+                    let mut thumbnail = None;
+                    let mut summary = None;
+                    while let (Some(name), Some(value)) = (sections.next(), sections.next()) {
+                        let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+                        match name {
+                            "THUMBNAIL" => thumbnail = value,
+                            "ABSTRACT" => summary = value,
+                            _ => return Err("Couldn't recognize gobbet section."),
+                        }
+                    }
+                    Ok(Self {
                         title: title.to_string(),
-                        date: match NaiveDate::parse_from_str(&date, "%Y/%m/%d") {
+                        date: match NaiveDate::parse_from_str(date, "%Y/%m/%d") {
                             Ok(d) => d,
                             _ => return Err("Couldn't parse date from str."),
                         },
@@ -611,20 +1226,126 @@ impl Publication {
                             .split(",")
                             .map(|s| s.to_string().trim().to_string())
                             .collect(),
-                    });
+                        thumbnail,
+                        summary,
+                    })
                 } else {
-                    return Err("Couldn't split at markdown.");
-                };
+                    Err("Couldn't split at markdown.")
+                }
             } else {
-                return Err("Couldn't split at date.");
-            };
+                Err("Couldn't split at date.")
+            }
         } else {
-            return Err("Couldn't split at title.");
+            Err("Couldn't split at title.")
+        }
+    }
+
+    fn is_paper(&self) -> bool {
+        self.tags.contains(&String::from("paper"))
+    }
+
+    /// Path of the page of the publication: papers are under "Publications",
+    /// and every other post under "Miscellaneous".
+    fn page_path(&self) -> String {
+        match self.is_paper() {
+            true => format!("publications-{}.html", titlefy_to_page_title(&self.title)),
+            false => format!("miscellaneous-{}.html", titlefy_to_page_title(&self.title)),
+        }
+    }
+
+    /// Path of the image that represents the publication: the thumbnail the
+    /// gobbet declares, or else the first local image of its markdown.
+    fn thumbnail_path(&self) -> Option<String> {
+        if let Some(thumbnail) = &self.thumbnail {
+            return Some(thumbnail.clone());
+        }
+
+        let mut rest = self.markdown.as_str();
+        while let Some((_, image)) = rest.split_once("![") {
+            rest = image;
+            if let Some((_, target)) = image.split_once("](") {
+                let path = target
+                    .split(|c: char| c == ')' || c.is_whitespace())
+                    .next()
+                    .unwrap_or("");
+                if !path.is_empty() && !path.contains("://") {
+                    return Some(path.to_string());
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Plain text of the abstract, or else of the first paragraph, cut at the
+    /// last word that fits in `max_chars` characters. Posts without
+    /// paragraphs, like lists, use all of their text.
+    fn excerpt(&self, refs: &FigureRefs, max_chars: usize) -> String {
+        let html = match &self.summary {
+            Some(summary) => markdown_to_html(summary, &ComrakOptions::default()),
+            None => self.to_html(refs),
         };
+        let paragraph = html
+            .split_once("<p>")
+            .and_then(|(_, p)| p.split_once("</p>"))
+            .map(|(p, _)| p)
+            .unwrap_or(&html);
+
+        let text = unescape_html(&strip_tags(paragraph))
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if text.chars().count() <= max_chars {
+            return text;
+        }
+        let cut = text.char_indices().nth(max_chars).unwrap().0;
+        let excerpt = match text[cut..].starts_with(' ') {
+            true => &text[..cut],
+            false => match text[..cut].rfind(' ') {
+                Some(space) => &text[..space],
+                None => &text[..cut],
+            },
+        };
+        format!("{}…", excerpt.trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace()))
+    }
+
+    /// Renders the markdown to HTML, resolving `@fig:<id>` references to
+    /// figures of the research studies first.
+    fn to_html(&self, refs: &FigureRefs) -> String {
+        markdown_to_html(&refs.resolve_markdown(&self.markdown), &ComrakOptions::default())
+            .to_string()
     }
 
-    fn to_html(&self) -> String {
-        markdown_to_html(&self.markdown, &ComrakOptions::default()).to_string()
+    /// HTML of the publication for its page, with its code blocks highlighted.
+    fn to_highlighted_html(&self, refs: &FigureRefs, highlighter: &Highlighter) -> String {
+        highlighter.markdown_to_html(&refs.resolve_markdown(&self.markdown))
+    }
+
+    fn pdf_path(&self) -> String {
+        format!("{}/{}.pdf", FOLDER_PUBLICATIONS, titlefy_to_page_title(&self.title))
+    }
+
+    /// Writes the publication by `author` to its PDF, with the links that
+    /// are relative to the site made absolute when its address is known.
+    fn write_pdf(&self, refs: &FigureRefs, config: &SiteConfig, author: &str) -> Result<(), &'static str> {
+        let resolve = |href: &str| match href.contains("://") || href.starts_with("mailto:") {
+            true => href.to_string(),
+            false => config.absolute_url(href),
+        };
+        let excerpt = self.excerpt(refs, config.homepage.excerpt_length);
+
+        let mut pdf = PdfWriter::new(&self.title, author, &excerpt, &self.tags, self.date)?;
+        pdf.heading(1, &[Span::new(&self.title, Style::Bold)]);
+        let mut byline = vec![Span::new(&format!("{}, {}", author, self.date), Style::Italic)];
+        if config.url.is_some() {
+            let url = config.absolute_url(&self.page_path());
+            byline.push(Span::new(" · ", Style::Regular));
+            byline.push(Span::link(&url, &url));
+        }
+        pdf.paragraph(&byline);
+        pdf.blocks(&blocks_from_html(&self.to_html(refs), &resolve));
+        pdf.save(&self.pdf_path())
     }
 }
 
@@ -678,178 +1399,112 @@ fn modify() -> Result<(), Box<dyn Error>> {
 }
 
 fn build() -> Result<(), Box<dyn Error>> {
-    let posts = Publication::from_gobbets_in_folder("publications").unwrap();
+    let site_config = SiteConfig::from_file(FILE_SITE_CONFIG)?;
+    let studies = Study::from_studies_in_folder(FOLDER_STUDIES)?;
 
-    let papers = posts
-        .into_iter()
-        .filter(|p| p.tags.contains(&String::from("paper")))
-        .collect::<Vec<_>>();
+    // Number every figure first, so that any page can reference them.
+    let mut figure_refs = FigureRefs::default();
+    for study in &studies {
+        study.add_figure_refs(&mut figure_refs);
+    }
 
-    for paper in &papers {
-        let mut page_paper = Page::new(&paper.title, CSS::Science);
-        page_paper.add_top_bar(
-            "profile_pic.png",
-            "Who am I?",
-            "Publications",
-            "Miscellaneous",
-            "CV",
-            "fa-person-rays",
-            "fa-atom",
-            "fa-cow",
-            "fa-address-book",
-            Some("Publications"),
-        );
-
-        let mut columns = page_paper.add_columns("");
-        let mut col = add_column_to_dual_columns(&mut columns);
-        let mut cont = col.div().attr("class='blank-container'");
-        write!(cont.h1(), "{}", paper.title)?;
-        write!(cont.h2(), "{}", paper.date)?;
-        write!(cont, "{}", paper.to_html())?;
+    // Lay an overlay over the background images so that text stays readable
+    // on top of them, and another one for readers who prefer a dark theme.
+    let overlay = &site_config.overlay;
+    let mut overlay_errors = false;
+    for style in std::iter::once(&overlay.light).chain(&overlay.dark) {
+        let report = apply_overlay_to_images(&overlay.source, style, site_config.modified)?;
+        report.print();
+        overlay_errors |= report.has_errors();
+    }
+    // Light backgrounds are also compressed, for the stylesheets that point
+    // at them before a picked background loads.
+    let compression = compress_images(&overlay.light.dest, &site_config.compression, site_config.modified)?;
+    compression.print();
 
-        page_paper.add_footer();
+    // Backgrounds are picked at random, in the size and format that fit the
+    // reader's screen and browser, by the script of every page.
+    let backgrounds = Backgrounds::from_config(&site_config, FOLDER_RESPONSIVE_IMAGES)?;
+    fs::create_dir_all(FOLDER_SCRIPTS)?;
+    backgrounds.write_script(&format!("{}/backgrounds.js", FOLDER_SCRIPTS))?;
 
-        page_paper
-            .publish(format!("publications-{}.html", titlefy_to_page_title(&paper.title)).as_str());
-    }
+    make_icons(&site_config)?;
 
-    // Apply white color with opacity of 0.9 to background images
-    // apply_white_overlay_to_images("img", "white-img");
-    compress_images("white-img", "compressed-img");
+    // Code blocks are highlighted when the pages are built, and colored by a
+    // stylesheet of the themes, so that pages need no script for them.
+    let highlighter = Highlighter::new(&site_config.highlight)?;
+    highlighter.write_css(FILE_HIGHLIGHT_CSS)?;
 
-    // Create "Homepage" Page
-    let mut page_homepage = Page::new("Homepage", CSS::Science);
+    let profile_pic = ResponsiveImage::from_file(
+        "profile_pic.png",
+        &format!("{}/profile", FOLDER_RESPONSIVE_IMAGES),
+    )?;
 
-    // Create "Who am I?" Page
-    let mut page_who_am_i = Page::new("Who am I?", CSS::Science);
+    // Tags are merged with their aliases first, so that every page agrees on
+    // them.
+    let taxonomy = Taxonomy::from_file(FILE_TAGS)?;
+    let mut posts = Publication::from_gobbets_in_folder(FOLDER_PUBLICATIONS).unwrap();
+    for post in &mut posts {
+        taxonomy.canonicalize(post);
+    }
 
-    // Create "Publications" Page
-    let mut page_publications = Page::new("Publications", CSS::Science);
+    let resume = Resume::from_file(FILE_CV)?;
+
+    // Thumbnails of the posts under "Miscellaneous", by title.
+    let mut thumbnails = HashMap::new();
+    for post in posts.iter().filter(|p| !p.is_paper()) {
+        if let Some(path) = post.thumbnail_path() {
+            let thumbnail = ResponsiveImage::from_file(
+                &path,
+                &format!("{}/thumbnails", FOLDER_RESPONSIVE_IMAGES),
+            )?;
+            thumbnails.insert(post.title.clone(), thumbnail);
+        }
+    }
 
-    // Create "Miscellaneous" Page
-    let mut page_miscellaneous = Page::new("Miscellaneous", CSS::Science);
+    for post in &posts {
+        let mut page_post = Page::new(&post.title, CSS::Science, &site_config, &backgrounds);
+        page_post.add_site_top_bar(
+            &profile_pic,
+            Some(match post.is_paper() {
+                true => "Publications",
+                false => "Miscellaneous",
+            }),
+        );
 
-    // Create "CV" Page
-    let mut page_cv = Page::new("CV", CSS::Science);
+        let mut columns = page_post.add_columns("");
+        let mut col = add_column_to_dual_columns(&mut columns);
+        let mut cont = col.div().attr("class='blank-container'");
+        if let Some(thumbnail) = thumbnails.get(&post.title) {
+            thumbnail.write_picture(&mut cont, "", "(max-width: 840px) 100vw, 50vw", "post-thumbnail");
+        }
+        write!(cont.h1(), "{}", post.title)?;
+        write!(cont.h2(), "{}", post.date)?;
+        write_tag_chips(&mut cont, &post.tags, &taxonomy);
+        if site_config.pdf.publications {
+            post.write_pdf(&figure_refs, &site_config, &resume.basics.name)?;
+            write!(
+                cont.a().attr(format!("class='btn btn-link' href='{}' download", post.pdf_path()).as_str()),
+                "<i class='fa-solid fa-file-pdf'></i> PDF"
+            )?;
+        }
+        write!(cont, "{}", post.to_highlighted_html(&figure_refs, &highlighter))?;
 
-    // Create "SMS" Pages
-    let mut page_sms_article_quality = Page::new_without_background("Systematic Mapping Study", CSS::Science);
-    let mut page_sms_article_individual = Page::new_without_background("Systematic Mapping Study", CSS::Science);
-    let mut page_sms_article_pairwise = Page::new_without_background("Systematic Mapping Study", CSS::Science);
+        page_post.add_footer();
 
-    // Add top bar to every page
-    page_homepage.add_top_bar(
-        "profile_pic.png",
-        "Who am I?",
-        "Publications",
-        "Miscellaneous",
-        "CV",
-        "fa-person-rays",
-        "fa-atom",
-        "fa-cow",
-        "fa-address-book",
-        None,
-    );
+        page_post.publish(&post.page_path());
+    }
 
-    
-    page_sms_article_quality.add_simple_top_bar(
-        "Quality<br><br>Assessment",
-        "Individual<br><br>Analysis",
-        "Pairwise<br><br>Analysis",
-        "Article<br><br>Page",
-        "fa-check",
-        "fa-folder",
-        "fa-folder-tree",
-        "fa-address-book",
-        Some("Quality<br><br>Assessment"),
-        Some("smsquality.html"),
-        Some("sms.html"),
-        Some("smspairwise.html"),
-        Some("https://scholar.google.com"),
-    );
+    build_tag_pages(&posts, &taxonomy, &site_config, &backgrounds, &profile_pic)?;
 
-    page_sms_article_individual.add_simple_top_bar(
-        "Quality<br><br>Assessment",
-        "Individual<br><br>Analysis",
-        "Pairwise<br><br>Analysis",
-        "Article<br><br>Page",
-        "fa-check",
-        "fa-folder",
-        "fa-folder-tree",
-        "fa-address-book",
-        Some("Individual<br><br>Analysis"),
-        Some("smsquality.html"),
-        Some("sms.html"),
-        Some("smspairwise.html"),
-        Some("https://scholar.google.com"),
-    );
+    let (papers, miscellaneous): (Vec<_>, Vec<_>) = posts.into_iter().partition(|p| p.is_paper());
 
-    page_sms_article_pairwise.add_simple_top_bar(
-        "Quality<br><br>Assessment",
-        "Individual<br><br>Analysis",
-        "Pairwise<br><br>Analysis",
-        "Article<br><br>Page",
-        "fa-check",
-        "fa-folder",
-        "fa-folder-tree",
-        "fa-address-book",
-        Some("Pairwise<br><br>Analysis"),
-        Some("smsquality.html"),
-        Some("sms.html"),
-        Some("smspairwise.html"),
-        Some("https://scholar.google.com"),
-    );
+    // Create "Homepage" Page
+    let mut page_homepage = Page::new("Homepage", CSS::Science, &site_config, &backgrounds);
 
-    /*
-    page_who_am_i.add_top_bar(
-        "profile_pic.jpg",
-        "Who am I?",
-        "Publications",
-        "Miscellaneous",
-        "CV",
-        "Who am I?",
-    );
-    page_publications.add_top_bar(
-        "profile_pic.jpg",
-        "Who am I?",
-        "Publications",
-        "Miscellaneous",
-        "CV",
-        "Publications",
-    );
-    page_miscellaneous.add_top_bar(
-        "profile_pic.jpg",
-        "Who am I?",
-        "Publications",
-        "Miscellaneous",
-        "CV",
-        "Miscellaneous",
-    );
-    page_cv.add_top_bar(
-        "profile_pic.jpg",
-        "Who am I?",
-        "Publications",
-        "Miscellaneous",
-        "CV",
-        "CV",
-    );
+    // Add top bar to every page
+    page_homepage.add_site_top_bar(&profile_pic, None);
 
-    // Add social media buttons
-    let social_media = vec![
-        (SocialMedia::Twitter, "cfpgomes"),
-        (SocialMedia::GoogleScholar, "cfpgomes"),
-        (SocialMedia::GitHub, "cfpgomes"),
-        (SocialMedia::LinkedIn, "cfpgomes"),
-        (SocialMedia::ORCID, "cfpgomes"),
-    ];
-
-    page_homepage.add_social_media_buttons(social_media);
-    page_who_am_i.add_social_media_buttons(social_media);
-    page_publications.add_social_media_buttons(social_media);
-    page_miscellaneous.add_social_media_buttons(social_media);
-    page_cv.add_social_media_buttons(social_media);
-
-    */
     //// "Homepage" Page Building process
 
     // Add "Who am I?" section to "Homepage" page
@@ -867,6 +1522,12 @@ fn build() -> Result<(), Box<dyn Error>> {
         container_intro.h3(),
         "Welcome to my webpage and feel free to contact me! 🤠"
     )?;
+    write!(
+        container_intro
+            .a()
+            .attr("class='btn btn-primary' href='who-am-i.html'"),
+        "<i class='fa-solid fa-person-rays'></i> More about me"
+    )?;
 
     let mut col_contacts = add_column_to_dual_columns(&mut columns_who_am_i_section);
     let mut container_contacts = col_contacts.div().attr("class='blank-container'");
@@ -930,82 +1591,79 @@ fn build() -> Result<(), Box<dyn Error>> {
     let mut container_pubs = col_pubs.div().attr("class='blank-container'");
     write!(container_pubs.h1().b(), "Check out my research! 💡")?;
 
-    // Get latest post
-    let latest_post = &papers[0];
-    let mut latest_post_container = container_pubs.a().attr(
-        format!(
-            "class='article_container' href='publications-{}.html'",
-            titlefy_to_page_title(&latest_post.title)
-        )
-        .as_str(),
-    );
-    write!(
-        latest_post_container.h4().attr("class='no_margin'").b(),
-        "{}",
-        latest_post.title
-    )?;
-    write!(
-        latest_post_container
-            .h5()
-            .attr("class='date_color no_margin'"),
-        "{}",
-        latest_post.date
-    )?;
-    write!(
-        latest_post_container
-            .p()
-            .attr("class='auto_crop no_margin'"),
-        "{}",
-        latest_post.markdown
-    )?;
+    // Add the latest publications
+    let homepage = &site_config.homepage;
+    for post in papers.iter().take(homepage.latest) {
+        let excerpt = post.excerpt(&figure_refs, homepage.excerpt_length);
+        add_post_card(&mut container_pubs, post, None, &excerpt)?;
+    }
+    if papers.is_empty() {
+        write!(container_pubs.h3(), "Nothing here yet, but stay tuned!")?;
+    }
 
-    // Get second latest post
-    let second_latest_post = &papers[1];
-    let mut second_latest_post_container = container_pubs.a().attr(
-        format!(
-            "class='article_container' href='publications-{}.html'",
-            titlefy_to_page_title(&second_latest_post.title)
-        )
-        .as_str(),
-    );
-    write!(
-        second_latest_post_container
-            .h4()
-            .attr("class='no_margin'")
-            .b(),
-        "{}",
-        second_latest_post.title
-    )?;
     write!(
-        second_latest_post_container
-            .h5()
-            .attr("class='date_color no_margin'"),
-        "{}",
-        second_latest_post.date
-    )?;
-    write!(
-        second_latest_post_container
-            .p()
-            .attr("class='auto_crop no_margin'"),
-        "{}",
-        second_latest_post.markdown
+        container_pubs
+            .a()
+            .attr("class='btn btn-primary' href='publications.html'"),
+        "<i class='fa-solid fa-atom'></i> See all publications"
     )?;
 
     let mut col_pubs = add_column_to_dual_columns(&mut columns_pub_misc_section);
     let mut container_pubs = col_pubs.div().attr("class='blank-container'");
     write!(container_pubs.h1().b(), "And also some random stuff...")?;
+    for post in miscellaneous.iter().take(homepage.latest) {
+        let excerpt = post.excerpt(&figure_refs, homepage.excerpt_length);
+        add_post_card(&mut container_pubs, post, thumbnails.get(&post.title), &excerpt)?;
+    }
+
+    write!(
+        container_pubs
+            .a()
+            .attr("class='btn btn-primary' href='miscellaneous.html'"),
+        "<i class='fa-solid fa-cow'></i> See all miscellaneous"
+    )?;
+    write!(
+        container_pubs
+            .a()
+            .attr("class='btn btn-primary' href='gallery.html'"),
+        "<i class='fa-solid fa-camera'></i> See my photos"
+    )?;
 
     // Add "CV" section to "Homepage" page
     let mut columns_cv_section = page_homepage.add_columns("");
     let mut col_cv_1 = add_column_to_dual_columns(&mut columns_cv_section);
     let mut container_cv_1 = col_cv_1.div().attr("class='blank-container'");
-    write!(container_cv_1.h1(), "Hey! I'm Cláudio Gomes.")?;
-    write!(container_cv_1.h3(), "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Id eu nisl nunc mi ipsum faucibus vitae aliquet nec. Congue eu consequat ac felis. Quisque sagittis purus sit amet volutpat consequat. Suspendisse in est ante in nibh mauris. Enim blandit volutpat maecenas volutpat blandit aliquam etiam erat velit. Mattis vulputate enim nulla aliquet. Aliquam ultrices sagittis orci a scelerisque purus semper eget. Viverra mauris in aliquam sem fringilla ut morbi. Egestas fringilla phasellus faucibus scelerisque eleifend. Volutpat sed cras ornare arcu. Enim lobortis scelerisque fermentum dui. Magna etiam tempor orci eu lobortis elementum nibh. Quis blandit turpis cursus in hac habitasse platea dictumst. Sed blandit libero volutpat sed cras ornare arcu dui.")?;
+    let basics = &resume.basics;
+    write!(container_cv_1.h1(), "Hey! I'm {}.", escape_html(&basics.name))?;
+    if let Some(summary) = basics.summary.as_ref().or(basics.label.as_ref()) {
+        write!(container_cv_1.h3(), "{}", escape_html(summary))?;
+    }
+    write!(
+        container_cv_1
+            .a()
+            .attr("class='btn btn-primary' href='cv.html'"),
+        "<i class='fa-solid fa-address-book'></i> See my CV"
+    )?;
 
     let mut col_cv_2 = add_column_to_dual_columns(&mut columns_cv_section);
     let mut container_cv_2 = col_cv_2.div().attr("class='blank-container'");
     write!(container_cv_2.h1(), "Here are my contacts:")?;
-    write!(container_cv_2.h3(), "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Id eu nisl nunc mi ipsum faucibus vitae aliquet nec. Congue eu consequat ac felis. Quisque sagittis purus sit amet volutpat consequat. Suspendisse in est ante in nibh mauris. Enim blandit volutpat maecenas volutpat blandit aliquam etiam erat velit. Mattis vulputate enim nulla aliquet. Aliquam ultrices sagittis orci a scelerisque purus semper eget. Viverra mauris in aliquam sem fringilla ut morbi. Egestas fringilla phasellus faucibus scelerisque eleifend. Volutpat sed cras ornare arcu. Enim lobortis scelerisque fermentum dui. Magna etiam tempor orci eu lobortis elementum nibh. Quis blandit turpis cursus in hac habitasse platea dictumst. Sed blandit libero volutpat sed cras ornare arcu dui.")?;
+    let mut contacts = container_cv_2.ul().attr("class='cv-contacts'");
+    if let Some(email) = &basics.email {
+        write!(
+            contacts.li().h3(),
+            "<a href='mailto:{0}'>{0}</a>",
+            escape_html(email)
+        )?;
+    }
+    for profile in &basics.profiles {
+        write!(
+            contacts.li().h3(),
+            "<a href='{}'>{}</a>",
+            escape_html(&profile.url),
+            escape_html(&profile.network)
+        )?;
+    }
 
     // Add footer to "Homepage" page
     page_homepage.add_footer();
@@ -1015,432 +1673,184 @@ fn build() -> Result<(), Box<dyn Error>> {
     page_homepage.publish("index.html");
 
     //// "Who am I?" Page Building process
-    // TODO: Descobrir depois o que meter, contar narrativa gira
+    build_who_am_i(FILE_ABOUT, FILE_TIMELINE, &site_config, &backgrounds, &profile_pic)?;
 
     //// "Publications" Page Building process
-    // Add every publication to this page, in the same way as in the homepage.
-    // Every publication should expand to read in its entirety.
-
-    //// "Miscellaneous" Page Building process
-    // Add every miscellaneous to this page, in the same way as in the homepage.
-    // Every miscellaneous should have a thumbnail as header.
-    // Every miscellaneous should expand to read in its entirety.
-
-    //// "CV" Page Building process
-    // TODO: Descobrir depois o que meter, estrutura gira de CV, printable to pdf idealmente
-
-    
-    //// "sms_article" Pages Building process
-    //// Quality Assessment Page
-    
-
-    let mut sms_quality_about = page_sms_article_quality.buf.div().attr("class='blank-container-square-half blank-background page-ultra-wide-treatment'");
-
-    write!(
-        sms_quality_about.h1(),
-        "Quality Assessment"
-    )?;
-
-    write!(
-        sms_quality_about.h4(),
-        "This page contains the quality assessment data in the paper \"A Systematic Mapping Study on Quantum and Quantum-inspired Algorithms in Operations Research\", submitted to ACM Computing Surveys. The results are presented in the following interactive charts. Each chart has controls that allow the user to inspect values by hovering the mouse over the data. The user can also zoom on the charts by selecting and dragging a region on the chart. The data used in this analysis is available in the repository in the following link:"
-    )?;
-    
-    write!(
-        sms_quality_about.a().attr("href='https://github.com/cfpgomes/sms-article-2'").h4(),
-        "https://github.com/cfpgomes/sms-article-2"
-    )?;
-
-    let mut sms_quality_title = page_sms_article_quality.buf.div().attr("class='blank-container-square-title page-ultra-wide-treatment'");
-
-    write!(
-        sms_quality_title.h1(),
-        "Charts"
-    )?;
-
-    let mut sms_quality_sstotal_vs_srtotal = page_sms_article_quality.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_quality_sstotal_vs_srtotal.h4(),
-        "Scatter plot of the S<sub>S</sub> and S<sub>R</sub> values of each of the selected studies. Blue dots represent the selected studies, and the red line is a visual aid to mark the diagonal of the chart. All the dots in a perfect positive correlation between both scores would overlap the line."
-    )?;
-    add_figure_to_node(&mut sms_quality_sstotal_vs_srtotal, "sms_figures/quality_sstotal_vs_srtotal.html");
-
-    let mut sms_quality_sstotal_vs_sjr = page_sms_article_quality.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_quality_sstotal_vs_sjr.h4(),
-        "Scatter plot of the S<sub>S</sub> value and SJR Indicator of each of the selected studies. Blue dots represent the selected studies."
-    )?;
-    add_figure_to_node(&mut sms_quality_sstotal_vs_sjr, "sms_figures/quality_sstotal_vs_sjr.html");
-
-    let mut sms_quality_srtotal_vs_sjr = page_sms_article_quality.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_quality_srtotal_vs_sjr.h4(),
-        "Scatter plot of the S<sub>R</sub> value and SJR Indicator of each of the selected studies. Blue dots represent the selected studies."
-    )?;
-    add_figure_to_node(&mut sms_quality_srtotal_vs_sjr, "sms_figures/quality_srtotal_vs_sjr.html");
-
-    let mut sms_quality_sstotal_vs_sjrquartile = page_sms_article_quality.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_quality_sstotal_vs_sjrquartile.h4(),
-        "Box plots of the S<sub>S</sub> value of the selected studies, grouped by their SJR Best Quartile. Blue dots represent the selected studies."
-    )?;
-    add_figure_to_node(&mut sms_quality_sstotal_vs_sjrquartile, "sms_figures/quality_sstotal_vs_sjrquartile.html");
-
-    let mut sms_quality_srtotal_vs_sjrquartile = page_sms_article_quality.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_quality_srtotal_vs_sjrquartile.h4(),
-        "Box plots of the S<sub>R</sub> value of the selected studies, grouped by their SJR Best Quartile. Blue dots represent the selected studies."
-    )?;
-    add_figure_to_node(&mut sms_quality_srtotal_vs_sjrquartile, "sms_figures/quality_srtotal_vs_sjrquartile.html");
-
-    let mut sms_quality_sstotal_vs_core = page_sms_article_quality.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_quality_sstotal_vs_core.h4(),
-        "Box plots of the S<sub>S</sub> value of the selected studies, grouped by their CORE Ranking. Blue dots represent the selected studies."
-    )?;
-    add_figure_to_node(&mut sms_quality_sstotal_vs_core, "sms_figures/quality_sstotal_vs_core.html");
-
-    let mut sms_quality_srtotal_vs_core = page_sms_article_quality.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_quality_srtotal_vs_core.h4(),
-        "Box plots of the S<sub>R</sub> value of the selected studies, grouped by their CORE Ranking. Blue dots represent the selected studies."
-    )?;
-    add_figure_to_node(&mut sms_quality_srtotal_vs_core, "sms_figures/quality_srtotal_vs_core.html");
-
-    let mut sms_individual_about = page_sms_article_individual.buf.div().attr("class='blank-container-square-half blank-background page-ultra-wide-treatment'");
-
-    write!(
-        sms_individual_about.h1(),
-        "Analysis of the Features"
-    )?;
-
-    write!(
-        sms_individual_about.h4(),
-        "This page contains the individual analysis of the features selected in the paper \"A Systematic Mapping Study on Quantum and Quantum-inspired Algorithms in Operations Research\", submitted to ACM Computing Surveys. Each feature is analyzed separately, and the results are presented in the following interactive charts. Each chart has controls that allow the user to inspect values by hovering the mouse over the data. The treemap and icicle charts also allow the user to filter the data by clicking on each block. The data used in this analysis is available in the repository in the following link:"
-    )?;
-    
-    write!(
-        sms_individual_about.a().attr("href='https://github.com/cfpgomes/sms-article-2'").h4(),
-        "https://github.com/cfpgomes/sms-article-2"
-    )?;
-
-    //// Individual Analysis Page
-    let mut sms_individual_title = page_sms_article_individual.buf.div().attr("class='blank-container-square-title page-ultra-wide-treatment'");
-    write!(
-        sms_individual_title.h1(),
-        "Charts"
-    )?;
-
-    let mut sms_individual_ss00_sr00 = page_sms_article_individual.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_individual_ss00_sr00.h4(),
-        "Violin plots of the S<sub>S</sub> and S<sub>R</sub> values of the selected studies. Black dots represent the outliers. The black dashed line represents the median."
-    )?;
-    add_figure_to_node(&mut sms_individual_ss00_sr00, "sms_figures/individual_ss00_sr00.html");
-    
-    let mut sms_individual_ss_counts = page_sms_article_individual.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_individual_ss_counts.h4(),
-        "Stacked bar charts with the proportions of the different responses (SS01 to SS10) collected for the checklist to assess the quality of the study."
-    )?;
-    add_figure_to_node(&mut sms_individual_ss_counts, "sms_figures/individual_ss_counts.html");
-    
-    let mut sms_individual_sr_counts = page_sms_article_individual.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_individual_sr_counts.h4(),
-        "Stacked bar charts with the proportions of the different responses (SR01 to SR16) collected for the checklist to assess the quality of the reporting."
-    )?;
-    add_figure_to_node(&mut sms_individual_sr_counts, "sms_figures/individual_sr_counts.html");
-
-    let mut sms_individual_D010 = page_sms_article_individual.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D010.h4(),
-        "Stacked bar chart with the proportions of the different responses collected for feature D010."
-    )?;
-    add_figure_to_node(&mut sms_individual_D010, "sms_figures/individual_D010.html");
-    
-    let mut sms_individual_D030 = page_sms_article_individual.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D030.h4(),
-        "Stacked bar chart with the proportions of the different responses collected for feature D030."
-    )?;
-    add_figure_to_node(&mut sms_individual_D030, "sms_figures/individual_D030.html");
-    
-    let mut sms_individual_D040 = page_sms_article_individual.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D040.h4(),
-        "Stacked bar chart with the proportions of the different responses collected for feature D040."
-    )?;
-    add_figure_to_node(&mut sms_individual_D040, "sms_figures/individual_D040.html");
-
-    let mut sms_individual_D070 = page_sms_article_individual.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D070.h4(),
-        "Stacked bar chart with the proportions of the different responses collected for feature D070."
-    )?;
-    add_figure_to_node(&mut sms_individual_D070, "sms_figures/individual_D070.html");
-
-    let mut sms_individual_D020 = page_sms_article_individual.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D020.h4(),
-        "Bar chart with the distribution of the different responses collected for feature D020."
-    )?;
-    add_figure_to_node(&mut sms_individual_D020, "sms_figures/individual_D020.html");
-
-    let mut sms_individual_D060 = page_sms_article_individual.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D060.h4(),
-        "Bar chart with the distribution of the different responses collected for feature D060. <b>Note that the bar for year 2022 only contains publications made in the first four months.</b>"
-    )?;
-    add_figure_to_node(&mut sms_individual_D060, "sms_figures/individual_D060.html");
-
-    
-    let mut sms_individual_D050_to_D053_treemap = page_sms_article_individual.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D050_to_D053_treemap.h4(),
-        "Treemap chart of the selected studies according to their section, division, group, and class from the International Standard Industrial Classification of All Economic Activities (ISIC)."
-    )?;
-    add_figure_to_node(&mut sms_individual_D050_to_D053_treemap, "sms_figures/individual_D050_to_D053_treemap.html");
-    
-    let mut sms_individual_D050_to_D053_icicle = page_sms_article_individual.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_individual_D050_to_D053_icicle.h4(),
-        "Icicle chart of the selected studies according to their section, division, group, and class from the International Standard Industrial Classification of All Economic Activities (ISIC)."
-    )?;
-    add_figure_to_node(&mut sms_individual_D050_to_D053_icicle, "sms_figures/individual_D050_to_D053_icicle.html");
-    
-    
-    let mut sms_pairwise_about = page_sms_article_pairwise.buf.div().attr("class='blank-container-square-half blank-background page-ultra-wide-treatment'");
-
-    write!(
-        sms_pairwise_about.h1(),
-        "Analysis of Relationships between Pairs of Features"
-    )?;
-
-    write!(
-        sms_pairwise_about.h4(),
-        "This page contains the analysis of the relationships between pairs of features selected in the paper \"A Systematic Mapping Study on Quantum and Quantum-inspired Algorithms in Operations Research\", submitted to ACM Computing Surveys. The results are presented in the following interactive charts. Each chart has controls that allow the user to inspect values by hovering the mouse over the data. The user can also zoom on the charts by selecting and dragging a region on the chart. The data used in this analysis is available in the repository in the following link:"
-    )?;
-    
-    write!(
-        sms_pairwise_about.a().attr("href='https://github.com/cfpgomes/sms-article-2'").h4(),
-        "https://github.com/cfpgomes/sms-article-2"
-    )?;
-
-    //// Pairwise Analysis Page
-    let mut sms_pairwise_title = page_sms_article_pairwise.buf.div().attr("class='blank-container-square-title page-ultra-wide-treatment'");
-    write!(
-        sms_pairwise_title.h1(),
-        "Charts"
-    )?;
-
-    let mut sms_pair_ss00_d010 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d010.h4(),
-        "<b>Pair 1:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by their type of approach (D010)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d010, "sms_figures/pair_ss00_d010.html");
-
-    let mut sms_pair_ss00_d020 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d020.h4(),
-        "<b>Pair 2:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by the four most frequent algorithm names (D020)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d020, "sms_figures/pair_ss00_d020.html");
-
-    let mut sms_pair_ss00_d030 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d030.h4(),
-        "<b>Pair 3:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by whether they use a simulator or a real quantum computer (D030)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d030, "sms_figures/pair_ss00_d030.html");
-
-    let mut sms_pair_ss00_d040 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d040.h4(),
-        "<b>Pair 4:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by the type of quantum computer (D040)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d040, "sms_figures/pair_ss00_d040.html");
-
-    let mut sms_pair_ss00_d050 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square-double-size page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d050.h4(),
-        "<b>Pair 5:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by the seven most frequent ISIC sections (D050)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d050, "sms_figures/pair_ss00_d050.html");
-
-    let mut sms_pair_ss00_d060 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square-double-size blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d060.h4(),
-        "<b>Pair 6:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by the publication year (D060)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d060, "sms_figures/pair_ss00_d060.html");
-
-    let mut sms_pair_ss00_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss00_d070.h4(),
-        "<b>Pair 7:</b> Violin plots of the distributions of the quality of the study scores (S<sub>S</sub>) of the selected studies, grouped by their publication source (D070)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss00_d070, "sms_figures/pair_ss00_d070.html");
-
-    let mut sms_pair_sr00_d010 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d010.h4(),
-        "<b>Pair 8:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by their type of approach (D010)."
-    )?;
-    add_figure_to_node(&mut sms_pair_sr00_d010, "sms_figures/pair_sr00_d010.html");
-
-    let mut sms_pair_sr00_d020 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d020.h4(),
-        "<b>Pair 9:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by the four most frequent algorithm names (D020)."
+    let listing_publications = Listing {
+        title: "Publications",
+        first_page: "publications.html",
+        folder: "",
+        config: &site_config.publications,
+    };
+    listing_publications.build(
+        &papers.iter().collect::<Vec<_>>(),
+        &HashMap::new(),
+        &taxonomy,
+        &site_config,
+        &backgrounds,
+        &profile_pic,
     )?;
-    add_figure_to_node(&mut sms_pair_sr00_d020, "sms_figures/pair_sr00_d020.html");
 
-    let mut sms_pair_sr00_d030 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d030.h4(),
-        "<b>Pair 10:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by whether they use a simulator or a real quantum computer (D030)."
-    )?;
-    add_figure_to_node(&mut sms_pair_sr00_d030, "sms_figures/pair_sr00_d030.html");
-
-    let mut sms_pair_sr00_d040 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d040.h4(),
-        "<b>Pair 11:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by the type of quantum computer (D040)."
-    )?;
-    add_figure_to_node(&mut sms_pair_sr00_d040, "sms_figures/pair_sr00_d040.html");
-
-    let mut sms_pair_sr00_d050 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square-double-size blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d050.h4(),
-        "<b>Pair 12:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by the seven most frequent ISIC sections (D050)."
-    )?;
-    add_figure_to_node(&mut sms_pair_sr00_d050, "sms_figures/pair_sr00_d050.html");
-
-    let mut sms_pair_sr00_d060 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square-double-size page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d060.h4(),
-        "<b>Pair 13:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by the publication year (D060)."
-    )?;
-    add_figure_to_node(&mut sms_pair_sr00_d060, "sms_figures/pair_sr00_d060.html");
-
-    let mut sms_pair_sr00_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_sr00_d070.h4(),
-        "<b>Pair 14:</b> Violin plots of the distributions of the quality of the reporting scores (S<sub>R</sub>) of the selected studies, grouped by their publication source (D070)."
-    )?;
-    add_figure_to_node(&mut sms_pair_sr00_d070, "sms_figures/pair_sr00_d070.html");
-
-    
-    let mut sms_pair_d010_d050 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d010_d050.h4(),
-        "<b>Pair 15:</b> Bar plot of the proportions of the selected studies according to their type of approach (D010), grouped by their ISIC section (D050)."
-    )?;
-    add_figure_to_node(&mut sms_pair_d010_d050, "sms_figures/pair_d010_d050.html");
-    
-    let mut sms_pair_d010_d060 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d010_d060.h4(),
-        "<b>Pair 16:</b> Bar plot of the proportions of the selected studies according to their type of approach (D010), grouped by their publication year (D060)."
-    )?;
-    add_figure_to_node(&mut sms_pair_d010_d060, "sms_figures/pair_d010_d060.html");
-
-    let mut sms_pair_d010_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d010_d070.h4(),
-        "<b>Pair 17:</b> Bar plot of the proportions of the selected studies according to their type of approach (D010), grouped by their publication source (D070)."
+    //// "Miscellaneous" Page Building process
+    let listing_miscellaneous = Listing {
+        title: "Miscellaneous",
+        first_page: "miscellaneous.html",
+        folder: "miscellaneous/",
+        config: &site_config.miscellaneous,
+    };
+    listing_miscellaneous.build(
+        &miscellaneous.iter().collect::<Vec<_>>(),
+        &thumbnails,
+        &taxonomy,
+        &site_config,
+        &backgrounds,
+        &profile_pic,
     )?;
-    add_figure_to_node(&mut sms_pair_d010_d070, "sms_figures/pair_d010_d070.html");
 
-    let mut sms_pair_d020_d050 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d020_d050.h4(),
-        "<b>Pair 18:</b> Bar plot of the counts of the selected studies according to their algorithm name (D020), grouped by their ISIC section (D050). Only studies belonging to the four most frequent algorithm names and to the seven most frequent ISIC sections are considered."
-    )?;
-    add_figure_to_node(&mut sms_pair_d020_d050, "sms_figures/pair_d020_d050.html");
-    
-    let mut sms_pair_d020_d060 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d020_d060.h4(),
-        "<b>Pair 19:</b> Bar plot of the counts of the selected studies according to their algorithm name (D020), grouped by their publication year (D060). Only studies belonging to the four most frequent algorithm names are considered."
-    )?;
-    add_figure_to_node(&mut sms_pair_d020_d060, "sms_figures/pair_d020_d060.html");
-    
-    let mut sms_pair_d020_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d020_d070.h4(),
-        "<b>Pair 20:</b> Bar plot of the counts of the selected studies according to their algorithm name (D020), grouped by their publication source (D070). Only studies belonging to the four most frequent algorithm names are considered."
-    )?;
-    add_figure_to_node(&mut sms_pair_d020_d070, "sms_figures/pair_d020_d070.html");
-    
-    let mut sms_pair_d050_d060 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d050_d060.h4(),
-        "<b>Pair 28:</b> Bar plot of the counts of the selected studies according to their ISIC section (D050), grouped by their publication year (D060). Only studies belonging to the seven most frequent ISIC sections are considered."
-    )?;
-    add_figure_to_node(&mut sms_pair_d050_d060, "sms_figures/pair_d050_d060.html");
+    //// "CV" Page Building process
+    build_cv(&resume, &papers, &taxonomy, &site_config, &backgrounds, &profile_pic)?;
 
-    let mut sms_pair_d050_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d050_d070.h4(),
-        "<b>Pair 29:</b> Bar plot of the counts of the selected studies according to their ISIC section (D050), grouped by their publication source (D070). Only studies belonging to the seven most frequent ISIC sections are considered."
+    //// "Gallery" Page Building process
+    let photos = Photo::from_config(
+        &site_config,
+        &format!("{}/gallery", FOLDER_RESPONSIVE_IMAGES),
     )?;
-    add_figure_to_node(&mut sms_pair_d050_d070, "sms_figures/pair_d050_d070.html");
+    build_gallery(&photos, &site_config, &backgrounds, &profile_pic)?;
 
-    let mut sms_pair_d060_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_d060_d070.h4(),
-        "<b>Pair 30:</b> Bar plot of the counts of the selected studies according to their publication source (D070), grouped by their publication year (D060)."
-    )?;
-    add_figure_to_node(&mut sms_pair_d060_d070, "sms_figures/pair_d070_d060.html");
+    //// Research study companion pages Building process
+    for study in &studies {
+        study.build(&figure_refs, &site_config)?;
+    }
 
+    // Every asset of the site, which the service worker keeps offline.
+    let images = backgrounds
+        .light
+        .iter()
+        .chain(&backgrounds.dark)
+        .chain([&profile_pic])
+        .chain(thumbnails.values())
+        .chain(photos.iter().map(|photo| &photo.image))
+        .collect::<Vec<_>>();
+    let asset_manifest = AssetManifest::from_site(&images)?;
+    asset_manifest.write(FILE_ASSET_MANIFEST)?;
+    write_service_worker(&site_config, &asset_manifest)?;
+
+    // Pages are built even when some image failed to be processed, but the
+    // build still fails so that it isn't published like that.
+    if overlay_errors {
+        return Err("Couldn't overlay every image.".into());
+    }
+    if compression.has_errors() {
+        return Err("Couldn't compress every image.".into());
+    }
 
-    let mut sms_pair_ss03_d040 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss03_d040.h4(),
-        "<b>Pair 31:</b> Bar plot of the counts of the selected studies according to their response to SS03, grouped by their type of quantum computer (D040)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss03_d040, "sms_figures/pair_ss03_d040.html");
+    Ok(())
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut sms_pair_ss03_d050 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss03_d050.h4(),
-        "<b>Pair 32:</b> Bar plot of the counts of the selected studies according to their response to SS03, grouped by their ISIC section (D050)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss03_d050, "sms_figures/pair_ss03_d050.html");
+    #[test]
+    fn publication_from_gobbet() {
+        assert!(Publication::from_gobbet("publications/test.gobbet").is_ok());
+    }
 
+    #[test]
+    fn figure_from_file() {
+        let figure = Figure::from_file("sms_figures/pair_ss00_d010.html").unwrap();
+        assert_eq!(figure.plotly_version, "2.14.0");
+        assert!(!figure.to_string().contains("cdn.plot.ly"));
+    }
 
-    let mut sms_pair_ss03_d060 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square blank-background page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss03_d060.h4(),
-        "<b>Pair 33:</b> Bar plot of the counts of the selected studies according to their response to SS03, grouped by their publication year (D060)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss03_d060, "sms_figures/pair_ss03_d060.html");
+    #[test]
+    fn lazy_figure_defers_plot() {
+        let figure = Figure::from_file("sms_figures/pair_ss00_d010.html").unwrap();
+        let mut html = String::new();
+        figure
+            .write_lazy(&mut html, Some(("figures/pair_ss00_d010.svg", String::from("Box plot"))))
+            .unwrap();
+
+        // Nothing is plotted until `loadFigure` runs, and the static chart is
+        // shown until then.
+        assert!(!html.contains("Plotly.newPlot"));
+        assert!(!html.contains("text/javascript"));
+        assert!(html.contains("<img class='figure-preview-img' src='figures/pair_ss00_d010.svg'"));
+        assert!(html.contains(&format!("onclick='loadFigure(\"{}\")'", figure.id)));
+
+        let (_, data) = html.split_once("<script type='application/json' class='figure-data'>").unwrap();
+        let (data, _) = data.split_once("</script>").unwrap();
+        let arguments: Vec<serde_json::Value> = serde_json::from_str(data).unwrap();
+        assert_eq!(arguments.len(), 4);
+        assert_eq!(arguments[0], figure.id.as_str());
+
+        let mut loader = String::new();
+        PlotlyRuntime::default().write_lazy_loader_once(&mut loader);
+        assert!(loader.contains("Plotly.newPlot.apply(Plotly, plot)"));
+    }
 
-    let mut sms_pair_ss03_d070 = page_sms_article_pairwise.buf.div().attr("class='blank-container-square page-ultra-wide-treatment'");
-    write!(
-        sms_pair_ss03_d070.h4(),
-        "<b>Pair 34:</b> Bar plot of the counts of the selected studies according to their response to SS03, grouped by their publication source (D070)."
-    )?;
-    add_figure_to_node(&mut sms_pair_ss03_d070, "sms_figures/pair_ss03_d070.html");
+    #[test]
+    fn plotly_runtime_written_once_per_page() {
+        let mut html = String::new();
+        let mut plotly = PlotlyRuntime::default();
+        for path in ["sms_figures/pair_ss00_d010.html", "sms_figures/pair_ss00_d020.html"] {
+            let figure = Figure::from_file(path).unwrap();
+            plotly.write_once(&mut html, &figure).unwrap();
+            write!(html, "{}", figure).unwrap();
+        }
+        assert_eq!(html.matches("<script src='js/plotly-2.14.0.min.js'>").count(), 1);
+        assert_eq!(html.matches("window.PlotlyConfig").count(), 1);
+        assert_eq!(html.matches("Plotly.newPlot").count(), 2);
 
-    //// Publish SMS pages
-    page_sms_article_quality.publish("smsquality.html");
-    page_sms_article_individual.publish("sms.html");
-    page_sms_article_pairwise.publish("smspairwise.html");
+        let mut other = Figure::from_file("sms_figures/pair_ss00_d010.html").unwrap();
+        other.plotly_version = String::from("2.15.0");
+        assert!(plotly.write_once(&mut html, &other).is_err());
+    }
 
-    Ok(())
-}
+    #[test]
+    fn publication_thumbnail_from_markdown() {
+        let date = NaiveDate::from_ymd_opt(2022, 5, 14).unwrap();
+        let mut post = Publication::new(
+            "Test",
+            date,
+            "![remote](https://example.com/a.jpg) ![local](img/carousel1.jpg \"Title\")",
+            vec![],
+        );
+        assert_eq!(post.thumbnail_path().unwrap(), "img/carousel1.jpg");
+        post.thumbnail = Some(String::from("img/carousel9.jpg"));
+        assert_eq!(post.thumbnail_path().unwrap(), "img/carousel9.jpg");
+        assert!(Publication::new("Test", date, "No images.", vec![]).thumbnail_path().is_none());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn publication_excerpt_cuts_at_word() {
+        let date = NaiveDate::from_ymd_opt(2022, 5, 14).unwrap();
+        let refs = FigureRefs::default();
+        let mut post = Publication::new(
+            "Test",
+            date,
+            "First **paragraph** & more.\n\nSecond paragraph.",
+            vec![],
+        );
+        assert_eq!(post.excerpt(&refs, 100), "First paragraph & more.");
+        assert_eq!(post.excerpt(&refs, 18), "First paragraph…");
+        post.summary = Some(String::from("The abstract."));
+        assert_eq!(post.excerpt(&refs, 100), "The abstract.");
+    }
 
     #[test]
-    fn publication_from_gobbet() {
-        assert!(Publication::from_gobbet("posts\\test.gobbet").is_ok());
+    fn page_escapes_title() {
+        let title = "Rock 'n' <Roll> & Co";
+        let escaped = "Rock &#39;n&#39; &lt;Roll&gt; &amp; Co";
+        let pages = [
+            Page::new(title, CSS::Science, &SiteConfig::default(), &Backgrounds::default()),
+            Page::new_without_background(title, CSS::Science, &SiteConfig::default()),
+        ];
+        for page in pages {
+            let html = page.buf.finish();
+            assert!(html.contains(&format!("Cláudio Gomes | {}", escaped)));
+            assert!(html.contains(&format!("<meta name='description' content='{}'>", escaped)));
+            assert!(!html.contains(title));
+        }
     }
 }
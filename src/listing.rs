@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+
+use html_builder::*;
+
+use super::images::{Backgrounds, ResponsiveImage};
+use super::site::{ListingConfig, SiteConfig};
+use super::tags::{write_tag_chips, Taxonomy};
+use super::{escape_html, titlefy_to_page_title, Page, Publication, CSS, FOLDER_SCRIPTS};
+
+/// Struct that represents a listing of posts, such as "Publications", split
+/// into pages and grouped by year. Readers can filter it by type and tag, and
+/// search the titles, across every page. Posts with a thumbnail show it on
+/// their card.
+pub(crate) struct Listing<'a> {
+    pub(crate) title: &'a str,
+    /// Path of the first page, which the others are numbered after.
+    pub(crate) first_page: &'a str,
+    /// Folder the other pages are in, as `{folder}page/2.html`.
+    pub(crate) folder: &'a str,
+    pub(crate) config: &'a ListingConfig,
+}
+
+impl<'a> Listing<'a> {
+    fn page_path(&self, number: usize) -> String {
+        match number {
+            1 => self.first_page.to_string(),
+            _ => format!("{}page/{}.html", self.folder, number),
+        }
+    }
+
+    /// Base of the relative links of the pages after the first, which are
+    /// as deep as their folder.
+    fn base(&self, number: usize) -> Option<String> {
+        match number {
+            1 => None,
+            _ => Some("../".repeat(self.page_path(number).matches('/').count())),
+        }
+    }
+
+    fn post_type(&self, post: &Publication) -> Option<&'a str> {
+        post_type(&self.config.types, post)
+    }
+
+    fn index_path(&self) -> String {
+        format!("{}/{}.js", FOLDER_SCRIPTS, titlefy_to_page_title(self.title))
+    }
+
+    /// Builds every page of the listing of `posts`, which are the most recent
+    /// first, and the index that the filters search. `thumbnails` are by
+    /// title.
+    pub(crate) fn build(
+        &self,
+        posts: &[&Publication],
+        thumbnails: &HashMap<String, ResponsiveImage>,
+        taxonomy: &Taxonomy,
+        config: &SiteConfig,
+        backgrounds: &Backgrounds,
+        profile_pic: &ResponsiveImage,
+    ) -> Result<(), Box<dyn Error>> {
+        self.write_index(posts, thumbnails, taxonomy)?;
+
+        // Tags the posts have, by name, to filter by.
+        let mut tags = posts
+            .iter()
+            .flat_map(|post| post.tags.iter())
+            .map(|tag| tag.as_str())
+            .collect::<Vec<_>>();
+        tags.sort_by_key(|tag| taxonomy.name(tag).to_lowercase());
+        tags.dedup();
+        let types = self
+            .config
+            .types
+            .iter()
+            .filter(|t| posts.iter().any(|post| self.post_type(post) == Some(t.as_str())))
+            .collect::<Vec<_>>();
+
+        let chunks = posts.chunks(self.config.page_size.max(1)).collect::<Vec<_>>();
+        let pages = chunks.len().max(1);
+        if pages > 1 && fs::create_dir_all(format!("{}page", self.folder)).is_err() {
+            return Err("Couldn't create listing pages folder.".into());
+        }
+        for number in 1..=pages {
+            let base = self.base(number);
+            let mut page = Page::new_with_meta(
+                self.title,
+                CSS::Science,
+                config,
+                backgrounds,
+                &[],
+                base.as_deref(),
+            );
+            page.add_site_top_bar(profile_pic, Some(self.title));
+
+            {
+                let mut container = page
+                    .buf
+                    .div()
+                    .attr("class='blank-container page-ultra-wide-treatment'");
+                write!(container.h1(), "{}", self.title)?;
+
+                let mut filters = container.div().attr("class='listing-filters'");
+                filters.input().attr(
+                    "class='form-input' type='search' id='listing-search-id' \
+                    placeholder='Search titles' aria-label='Search titles'",
+                );
+                if !types.is_empty() {
+                    let mut select = filters
+                        .select()
+                        .attr("class='form-select' id='listing-type-id' aria-label='Type'");
+                    write!(select.option().attr("value=''"), "Every type")?;
+                    for t in &types {
+                        write!(
+                            select.option().attr(format!("value='{}'", escape_html(t)).as_str()),
+                            "{}",
+                            escape_html(taxonomy.name(t))
+                        )?;
+                    }
+                }
+                let mut select = filters
+                    .select()
+                    .attr("class='form-select' id='listing-tag-id' aria-label='Tag'");
+                write!(select.option().attr("value=''"), "Every tag")?;
+                for tag in &tags {
+                    write!(
+                        select.option().attr(format!("value='{}'", escape_html(tag)).as_str()),
+                        "{}",
+                        escape_html(taxonomy.name(tag))
+                    )?;
+                }
+            }
+
+            page.buf
+                .div()
+                .attr("class='blank-container page-ultra-wide-treatment' id='listing-results-id' hidden");
+
+            {
+                let mut listing = page
+                    .buf
+                    .div()
+                    .attr("class='blank-container page-ultra-wide-treatment' id='listing-page-id'");
+                let mut year = None;
+                for post in chunks.get(number - 1).copied().unwrap_or_default() {
+                    if year != Some(post.date.format("%Y").to_string()) {
+                        year = Some(post.date.format("%Y").to_string());
+                        write!(listing.h2().attr("class='listing-year'"), "{}", year.as_ref().unwrap())?;
+                    }
+                    self.write_card(&mut listing, post, thumbnails.get(&post.title), taxonomy);
+                }
+                if posts.is_empty() {
+                    write!(listing.p(), "Nothing here yet.")?;
+                }
+            }
+
+            if pages > 1 {
+                let mut pagination = page
+                    .buf
+                    .nav()
+                    .attr("class='listing-pagination' id='listing-pagination-id' aria-label='Pages'");
+                self.write_pagination(&mut pagination, number, pages);
+            }
+
+            write!(
+                page.buf.script().attr(format!("src='{}'", self.index_path()).as_str()),
+                ""
+            )?;
+            write!(page.buf.script(), "{}", FILTER_SCRIPT)?;
+
+            page.add_footer();
+            page.publish(&self.page_path(number));
+        }
+
+        Ok(())
+    }
+
+    fn write_card(
+        &self,
+        w: &mut dyn Write,
+        post: &Publication,
+        thumbnail: Option<&ResponsiveImage>,
+        taxonomy: &Taxonomy,
+    ) {
+        write!(w, "<div class='listing-card'>").unwrap();
+        if let Some(thumbnail) = thumbnail {
+            write!(w, "<a class='listing-thumbnail' href='{}' tabindex='-1'>", post.page_path()).unwrap();
+            thumbnail.write_picture(w, "", THUMBNAIL_SIZES, "listing-thumbnail-img");
+            write!(w, "</a>").unwrap();
+        }
+        write!(
+            w,
+            "<div><a href='{}'><h4 class='no_margin'>{}</h4></a>\
+            <h5 class='date_color no_margin'>{}",
+            post.page_path(),
+            escape_html(&post.title),
+            post.date
+        )
+        .unwrap();
+        if let Some(t) = self.post_type(post) {
+            write!(w, " · {}", escape_html(taxonomy.name(t))).unwrap();
+        }
+        write!(w, "</h5>").unwrap();
+        write_tag_chips(w, &post.tags, taxonomy);
+        write!(w, "</div></div>").unwrap();
+    }
+
+    fn write_pagination(&self, w: &mut dyn Write, number: usize, pages: usize) {
+        write!(w, "<ul class='pagination'>").unwrap();
+        let item = |w: &mut dyn Write, target: usize, label: &str, class: &str| {
+            write!(
+                w,
+                "<li class='page-item {}'><a href='{}'>{}</a></li>",
+                class,
+                self.page_path(target),
+                label
+            )
+            .unwrap();
+        };
+        match number {
+            1 => write!(w, "<li class='page-item disabled'><a>Previous</a></li>").unwrap(),
+            _ => item(w, number - 1, "Previous", ""),
+        }
+        for target in 1..=pages {
+            match target == number {
+                true => item(w, target, &target.to_string(), "active"),
+                false => item(w, target, &target.to_string(), ""),
+            }
+        }
+        match number == pages {
+            true => write!(w, "<li class='page-item disabled'><a>Next</a></li>").unwrap(),
+            false => item(w, number + 1, "Next", ""),
+        }
+        write!(w, "</ul>").unwrap();
+    }
+
+    /// Writes every post, with what the filters match, to a script.
+    fn write_index(
+        &self,
+        posts: &[&Publication],
+        thumbnails: &HashMap<String, ResponsiveImage>,
+        taxonomy: &Taxonomy,
+    ) -> Result<(), &'static str> {
+        let index = posts
+            .iter()
+            .map(|post| {
+                serde_json::json!({
+                    "title": post.title,
+                    "url": post.page_path(),
+                    "date": post.date.to_string(),
+                    "type": self.post_type(post),
+                    "typeName": self.post_type(post).map(|t| taxonomy.name(t)),
+                    "thumbnail": thumbnails.get(&post.title).map(|t| t.jpeg_path(THUMBNAIL_WIDTH)),
+                    "tags": post
+                        .tags
+                        .iter()
+                        .map(|tag| serde_json::json!({ "tag": tag, "name": taxonomy.name(tag), "url": super::tags::tag_page_path(tag) }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if fs::create_dir_all(FOLDER_SCRIPTS).is_err() {
+            return Err("Couldn't create scripts folder.");
+        }
+        match fs::write(
+            self.index_path(),
+            format!("var listingIndex = {};", serde_json::to_string(&index).unwrap()),
+        ) {
+            Ok(_) => Ok(()),
+            _ => Err("Couldn't write listing index."),
+        }
+    }
+}
+
+/// Type of `post`, which is the first of `types` that it is tagged with.
+pub(crate) fn post_type<'a>(types: &'a [String], post: &Publication) -> Option<&'a str> {
+    types
+        .iter()
+        .find(|t| post.tags.contains(t))
+        .map(|t| t.as_str())
+}
+
+/// Width, in pixels, that thumbnails are shown at on cards.
+const THUMBNAIL_WIDTH: u32 = 160;
+const THUMBNAIL_SIZES: &str = "160px";
+
+/// Shows the posts of the index that match the filters in place of the page,
+/// or the page again when no filter is set. Filters can also be set by the
+/// `q`, `type` and `tag` parameters of the address.
+const FILTER_SCRIPT: &str = "
+        var listingSearch = document.getElementById('listing-search-id');
+        var listingType = document.getElementById('listing-type-id');
+        var listingTag = document.getElementById('listing-tag-id');
+
+        function listingElement(tag, className, text) {
+            var element = document.createElement(tag);
+            element.className = className;
+            element.textContent = text;
+            return element;
+        }
+
+        function filterListing() {
+            var query = listingSearch.value.trim().toLowerCase();
+            var type = listingType ? listingType.value : '';
+            var tag = listingTag.value;
+            var active = query !== '' || type !== '' || tag !== '';
+
+            var results = document.getElementById('listing-results-id');
+            var pagination = document.getElementById('listing-pagination-id');
+            document.getElementById('listing-page-id').hidden = active;
+            if (pagination) {
+                pagination.hidden = active;
+            }
+            results.hidden = !active;
+            if (!active) {
+                return;
+            }
+
+            var matches = listingIndex.filter(function(post) {
+                return (query === '' || post.title.toLowerCase().includes(query))
+                    && (type === '' || post.type === type)
+                    && (tag === '' || post.tags.some(function(t) { return t.tag === tag; }));
+            });
+
+            results.replaceChildren();
+            if (matches.length === 0) {
+                results.appendChild(listingElement('p', '', 'Nothing matches the filters.'));
+                return;
+            }
+            var year = null;
+            matches.forEach(function(post) {
+                if (post.date.slice(0, 4) !== year) {
+                    year = post.date.slice(0, 4);
+                    results.appendChild(listingElement('h2', 'listing-year', year));
+                }
+                var card = listingElement('div', 'listing-card', '');
+                if (post.thumbnail) {
+                    var thumbnailLink = listingElement('a', 'listing-thumbnail', '');
+                    thumbnailLink.href = post.url;
+                    thumbnailLink.tabIndex = -1;
+                    var thumbnail = listingElement('img', 'listing-thumbnail-img', '');
+                    thumbnail.src = post.thumbnail;
+                    thumbnail.alt = '';
+                    thumbnail.loading = 'lazy';
+                    thumbnailLink.appendChild(thumbnail);
+                    card.appendChild(thumbnailLink);
+                }
+                var text = document.createElement('div');
+                var link = document.createElement('a');
+                link.href = post.url;
+                link.appendChild(listingElement('h4', 'no_margin', post.title));
+                text.appendChild(link);
+                text.appendChild(listingElement('h5', 'date_color no_margin',
+                    post.date + (post.typeName ? ' · ' + post.typeName : '')));
+                var chips = listingElement('div', 'tag-chips', '');
+                post.tags.forEach(function(t) {
+                    var chip = listingElement('a', 'chip', t.name);
+                    chip.href = t.url;
+                    chips.appendChild(chip);
+                });
+                text.appendChild(chips);
+                card.appendChild(text);
+                results.appendChild(card);
+            });
+        }
+
+        var listingParams = new URLSearchParams(window.location.search);
+        listingSearch.value = listingParams.get('q') || '';
+        if (listingType) {
+            listingType.value = listingParams.get('type') || '';
+            listingType.addEventListener('change', filterListing);
+        }
+        listingTag.value = listingParams.get('tag') || '';
+        listingSearch.addEventListener('input', filterListing);
+        listingTag.addEventListener('change', filterListing);
+        filterListing();
+        ";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_pages_link_to_each_other() {
+        let config = ListingConfig::default();
+        let listing = Listing {
+            title: "Miscellaneous",
+            first_page: "miscellaneous.html",
+            folder: "miscellaneous/",
+            config: &config,
+        };
+        assert_eq!(listing.page_path(1), "miscellaneous.html");
+        assert_eq!(listing.page_path(3), "miscellaneous/page/3.html");
+        assert_eq!(listing.base(1), None);
+        assert_eq!(listing.base(3).as_deref(), Some("../../"));
+
+        let mut html = String::new();
+        listing.write_pagination(&mut html, 1, 3);
+        assert!(html.starts_with("<ul class='pagination'><li class='page-item disabled'><a>Previous</a></li>"));
+        assert!(html.contains("<li class='page-item active'><a href='miscellaneous.html'>1</a></li>"));
+        assert!(html.contains("<li class='page-item '><a href='miscellaneous/page/2.html'>Next</a></li>"));
+
+        let mut html = String::new();
+        listing.write_pagination(&mut html, 3, 3);
+        assert!(html.contains("<li class='page-item '><a href='miscellaneous/page/2.html'>Previous</a></li>"));
+        assert!(html.contains("<li class='page-item active'><a href='miscellaneous/page/3.html'>3</a></li>"));
+        assert!(html.ends_with("<li class='page-item disabled'><a>Next</a></li></ul>"));
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::icons::{FILE_FAVICON, FILE_WEB_MANIFEST, FOLDER_ICONS};
+use super::images::ResponsiveImage;
+use super::site::SiteConfig;
+use super::{FOLDER_DATA, FOLDER_FIGURES, FOLDER_RESPONSIVE_IMAGES, FOLDER_SCRIPTS};
+
+pub(crate) const FILE_ASSET_MANIFEST: &str = "asset-manifest.json";
+pub(crate) const FILE_SERVICE_WORKER: &str = "sw.js";
+
+/// Folders the build writes assets to, besides the pages at the root and the
+/// variants of the images, whose names change with their sources.
+const ASSET_FOLDERS: [&str; 7] = [
+    "css",
+    "svg",
+    FOLDER_SCRIPTS,
+    FOLDER_FIGURES,
+    FOLDER_DATA,
+    FOLDER_ICONS,
+    "compressed-img",
+];
+
+/// Struct that represents every asset of the built site, by path, with a hash
+/// of its content.
+pub(crate) struct AssetManifest {
+    assets: BTreeMap<String, String>,
+}
+
+impl AssetManifest {
+    /// Lists and hashes the pages at the root, the files in the asset folders
+    /// and the variants of `images`, which are the ones this build made, so
+    /// that variants of older versions of them are left out.
+    pub(crate) fn from_site(images: &[&ResponsiveImage]) -> Result<Self, &'static str> {
+        Self::from_folder(Path::new("."), images)
+    }
+
+    fn from_folder(root: &Path, images: &[&ResponsiveImage]) -> Result<Self, &'static str> {
+        let mut paths = Vec::new();
+        let entries = match fs::read_dir(root) {
+            Ok(e) => e,
+            _ => return Err("Couldn't open folder."),
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".html") || name == FILE_FAVICON || name == FILE_WEB_MANIFEST {
+                paths.push(entry.path());
+            }
+        }
+        for folder in ASSET_FOLDERS {
+            add_files_in_folder(&root.join(folder), &mut paths);
+        }
+        paths.extend(images.iter().flat_map(|image| image.paths()).map(PathBuf::from));
+
+        let mut assets = BTreeMap::new();
+        for path in paths {
+            let content = match fs::read(&path) {
+                Ok(c) => c,
+                _ => return Err("Couldn't read asset."),
+            };
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            assets.insert(
+                relative.to_string_lossy().replace('\\', "/"),
+                format!("{:x}", Sha256::digest(&content))[..12].to_string(),
+            );
+        }
+
+        Ok(Self { assets })
+    }
+
+    /// Hash of every asset together, which changes whenever any asset does.
+    pub(crate) fn version(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (path, hash) in &self.assets {
+            hasher.update(path.as_bytes());
+            hasher.update(hash.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())[..12].to_string()
+    }
+
+    pub(crate) fn write(&self, manifest_path: &str) -> Result<(), &'static str> {
+        let json = serde_json::json!({
+            "version": self.version(),
+            "assets": self.assets,
+        });
+        match fs::write(manifest_path, serde_json::to_string_pretty(&json).unwrap()) {
+            Ok(_) => Ok(()),
+            _ => Err("Couldn't write asset manifest."),
+        }
+    }
+
+    /// Assets the service worker keeps offline: pages, stylesheets, scripts,
+    /// figures and icons. Downloads are left out, and so are the variants of
+    /// the images, which are cached once shown, in the size and format the
+    /// browser picked.
+    fn precache(&self) -> Vec<&str> {
+        self.assets
+            .keys()
+            .map(|path| path.as_str())
+            .filter(|path| {
+                let folder = path.split('/').next().unwrap();
+                folder != FOLDER_DATA && folder != "compressed-img" && folder != FOLDER_RESPONSIVE_IMAGES
+            })
+            .collect()
+    }
+}
+
+fn add_files_in_folder(folder: &Path, paths: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(folder) {
+        Ok(e) => e,
+        _ => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            add_files_in_folder(&path, paths);
+        } else {
+            paths.push(path);
+        }
+    }
+}
+
+/// Writes the service worker, which keeps the assets of `manifest` offline
+/// when the config enables it. Otherwise, it writes one that removes itself
+/// and its caches, for readers who installed it while it was enabled.
+pub(crate) fn write_service_worker(config: &SiteConfig, manifest: &AssetManifest) -> Result<(), &'static str> {
+    let script = match config.offline.enabled {
+        true => {
+            let mut precache = vec!["./"];
+            precache.extend(manifest.precache());
+            format!(
+                "\
+const CACHE = 'site-{}';
+const RUNTIME_CACHE = 'runtime';
+const IMAGE_CACHE = 'images';
+const IMAGES = self.registration.scope + '{}/';
+const PRECACHE = {};
+
+self.addEventListener('install', function(event) {{
+    event.waitUntil(caches.open(CACHE).then(function(cache) {{
+        return cache.addAll(PRECACHE);
+    }}).then(function() {{
+        return self.skipWaiting();
+    }}));
+}});
+
+// Caches of older builds are removed once this one takes over, and so are
+// the images that aren't in its asset manifest anymore.
+self.addEventListener('activate', function(event) {{
+    event.waitUntil(caches.keys().then(function(keys) {{
+        return Promise.all(keys.filter(function(key) {{
+            return key !== CACHE && key !== RUNTIME_CACHE && key !== IMAGE_CACHE;
+        }}).map(function(key) {{
+            return caches.delete(key);
+        }}));
+    }}).then(function() {{
+        return fetch('{}', {{ cache: 'no-store' }});
+    }}).then(function(response) {{
+        return response.json();
+    }}).then(function(manifest) {{
+        return caches.open(IMAGE_CACHE).then(function(cache) {{
+            return cache.keys().then(function(requests) {{
+                return Promise.all(requests.filter(function(request) {{
+                    return !(request.url.slice(self.registration.scope.length) in manifest.assets);
+                }}).map(function(request) {{
+                    return cache.delete(request);
+                }}));
+            }});
+        }});
+    }}).catch(function() {{}}).then(function() {{
+        return self.clients.claim();
+    }}));
+}});
+
+// Responds with the cached copy right away, if there is one, and updates it
+// from the network for the next visit.
+function staleWhileRevalidate(event, cacheName) {{
+    return caches.open(cacheName).then(function(cache) {{
+        return cache.match(event.request).then(function(cached) {{
+            var network = fetch(event.request).then(function(response) {{
+                if (response.ok || response.type === 'opaque') {{
+                    cache.put(event.request, response.clone());
+                }}
+                return response;
+            }});
+            if (cached) {{
+                event.waitUntil(network.catch(function() {{}}));
+                return cached;
+            }}
+            return network;
+        }});
+    }});
+}}
+
+self.addEventListener('fetch', function(event) {{
+    var request = event.request;
+    if (request.method !== 'GET') {{
+        return;
+    }}
+
+    // Stylesheets, scripts and fonts from CDNs, without which pages fall apart.
+    if (new URL(request.url).origin !== self.location.origin) {{
+        if (['style', 'script', 'font'].includes(request.destination)) {{
+            event.respondWith(staleWhileRevalidate(event, RUNTIME_CACHE));
+        }}
+        return;
+    }}
+
+    // Variants of the images are named after their sources, so a cached one
+    // never goes stale.
+    if (request.url.startsWith(IMAGES)) {{
+        event.respondWith(caches.open(IMAGE_CACHE).then(function(cache) {{
+            return cache.match(request).then(function(cached) {{
+                return cached || fetch(request).then(function(response) {{
+                    if (response.ok) {{
+                        cache.put(request, response.clone());
+                    }}
+                    return response;
+                }});
+            }});
+        }}));
+        return;
+    }}
+
+    if (request.mode === 'navigate' || request.destination === 'document') {{
+        event.respondWith(staleWhileRevalidate(event, CACHE));
+        return;
+    }}
+
+    event.respondWith(caches.match(request).then(function(cached) {{
+        return cached || fetch(request);
+    }}));
+}});
+",
+                manifest.version(),
+                FOLDER_RESPONSIVE_IMAGES,
+                serde_json::to_string_pretty(&precache).unwrap(),
+                FILE_ASSET_MANIFEST
+            )
+        }
+        false => {
+            // Only readers who installed it request it, so it isn't written
+            // for sites that never enabled it.
+            if !Path::new(FILE_SERVICE_WORKER).exists() {
+                return Ok(());
+            }
+            String::from(
+                "\
+self.addEventListener('install', function() {
+    self.skipWaiting();
+});
+
+self.addEventListener('activate', function(event) {
+    event.waitUntil(caches.keys().then(function(keys) {
+        return Promise.all(keys.map(function(key) {
+            return caches.delete(key);
+        }));
+    }).then(function() {
+        return self.registration.unregister();
+    }));
+});
+",
+            )
+        }
+    };
+
+    match fs::write(FILE_SERVICE_WORKER, script) {
+        Ok(_) => Ok(()),
+        _ => Err("Couldn't write service worker."),
+    }
+}
+
+/// Script that registers the service worker, which pages only include when
+/// the config enables it.
+pub(crate) fn registration_script() -> String {
+    format!(
+        "
+        if ('serviceWorker' in navigator) {{
+            window.addEventListener('load', function() {{
+                navigator.serviceWorker.register('{}');
+            }});
+        }}
+        ",
+        FILE_SERVICE_WORKER
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    #[test]
+    fn manifest_lists_current_image_variants_only() {
+        let root = std::env::temp_dir().join("personal-website-asset-manifest");
+        let _ = fs::remove_dir_all(&root);
+        let gallery = root.join(FOLDER_RESPONSIVE_IMAGES).join("gallery");
+        fs::create_dir_all(root.join("css")).unwrap();
+        fs::create_dir_all(root.join(FOLDER_DATA)).unwrap();
+        fs::create_dir_all(&gallery).unwrap();
+        fs::write(root.join("index.html"), "<html></html>").unwrap();
+        fs::write(root.join("notes.txt"), "Not an asset.").unwrap();
+        fs::write(root.join("css/site.css"), "body {}").unwrap();
+        fs::write(root.join(FOLDER_DATA).join("study.zip"), "zip").unwrap();
+        // Variant of an older version of the photo, which this build didn't make.
+        fs::write(gallery.join("photo-000000000000-16.jpg"), "old").unwrap();
+
+        let source = root.join("photo.png");
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 8, Rgb([146, 212, 171])))
+            .save(&source)
+            .unwrap();
+        let photo = ResponsiveImage::from_file(source.to_str().unwrap(), gallery.to_str().unwrap()).unwrap();
+
+        let manifest = AssetManifest::from_folder(&root, &[&photo]).unwrap();
+        let variants = photo
+            .paths()
+            .map(|path| Path::new(path).strip_prefix(&root).unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let mut expected = vec![
+            String::from("css/site.css"),
+            format!("{}/study.zip", FOLDER_DATA),
+            String::from("index.html"),
+        ];
+        expected.extend(variants.iter().cloned());
+        expected.sort();
+        assert_eq!(manifest.assets.keys().cloned().collect::<Vec<_>>(), expected);
+        assert_eq!(variants.len(), 3);
+        assert!(variants.iter().all(|v| v.starts_with("responsive-img/gallery/photo-")));
+        assert!(manifest.assets.values().all(|hash| hash.len() == 12));
+
+        // Downloads and images aren't precached.
+        assert_eq!(manifest.precache(), ["css/site.css", "index.html"]);
+
+        let version = manifest.version();
+        fs::write(root.join("css/site.css"), "body { margin: 0; }").unwrap();
+        assert_ne!(AssetManifest::from_folder(&root, &[&photo]).unwrap().version(), version);
+    }
+}
@@ -0,0 +1,747 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use chrono::NaiveDate;
+
+use printpdf::{
+    Actions, BorderArray, BuiltinFont, Color, ColorArray, IndirectFontRef, LinkAnnotation, Mm,
+    OffsetDateTime, PdfDocument, PdfDocumentReference, PdfLayerReference, Rect, Rgb,
+};
+
+use super::unescape_html;
+
+/// Size of the pages, A4, and of their margins, in millimeters.
+const PAGE_WIDTH: f32 = 210.;
+const PAGE_HEIGHT: f32 = 297.;
+const MARGIN: f32 = 20.;
+/// Height of the footer with the page number, from the bottom of the page.
+const FOOTER: f32 = 12.;
+
+/// Font sizes, in points, of the text and of the headings of every level.
+const TEXT_SIZE: f32 = 10.5;
+const HEADING_SIZES: [f32; 6] = [20., 14., 12., 11., 10.5, 10.5];
+const CODE_SIZE: f32 = 9.;
+const FOOTER_SIZE: f32 = 8.;
+/// Height of a line, as a multiple of its font size.
+const LINE_SPACING: f32 = 1.35;
+
+const POINT: f32 = 0.3528;
+const LINK_COLOR: (f32, f32, f32) = (0.18, 0.34, 0.62);
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of
+/// the font size, as its metrics give them. Other characters are counted as
+/// wide as most letters.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+/// How much wider the bold letters are than the regular ones, on average.
+const BOLD_SCALE: f32 = 1.06;
+
+/// Enum to enumerate the styles of text: the three of Helvetica, and
+/// Courier for code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Style {
+    Regular,
+    Bold,
+    Italic,
+    Code,
+}
+
+/// Struct that represents a run of text of the same style, which is a link
+/// when it has a `link`.
+#[derive(Debug, Clone)]
+pub(crate) struct Span {
+    pub(crate) text: String,
+    pub(crate) style: Style,
+    pub(crate) link: Option<String>,
+}
+
+impl Span {
+    pub(crate) fn new(text: &str, style: Style) -> Self {
+        Self {
+            text: text.to_string(),
+            style,
+            link: None,
+        }
+    }
+
+    pub(crate) fn link(text: &str, url: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            style: Style::Regular,
+            link: Some(url.to_string()),
+        }
+    }
+}
+
+/// Enum to enumerate the blocks of a document, as they are laid out.
+#[derive(Debug)]
+pub(crate) enum Block {
+    Heading(usize, Vec<Span>),
+    Paragraph(Vec<Span>),
+    /// Item of a list, after its marker.
+    Item(String, Vec<Span>),
+    Code(String),
+}
+
+/// Width of `text` in `style`, in millimeters, at `size` points.
+fn text_width(text: &str, style: Style, size: f32) -> f32 {
+    let thousandths = text
+        .chars()
+        .map(|c| match style {
+            Style::Code => 600.,
+            _ => {
+                let width = match c as u32 {
+                    32..=126 => HELVETICA_WIDTHS[c as usize - 32] as f32,
+                    _ => 556.,
+                };
+                match style {
+                    Style::Bold => width * BOLD_SCALE,
+                    _ => width,
+                }
+            }
+        })
+        .sum::<f32>();
+    thousandths / 1000. * size * POINT
+}
+
+/// Lines of `spans` wrapped to `width` millimeters at `size` points, as the
+/// pieces of the spans on every line with their distance from its start.
+/// Lines break between words, and at the line breaks of the spans; words
+/// wider than a line are broken across lines.
+fn lay_out(spans: &[Span], size: f32, width: f32) -> Vec<Vec<(f32, Span)>> {
+    // Words, as the pieces of the spans they are made of, and whether
+    // they start a line.
+    let mut words: Vec<(Vec<Span>, bool)> = vec![(Vec::new(), false)];
+    for span in spans {
+        let mut piece = String::new();
+        for c in span.text.chars() {
+            if c.is_whitespace() {
+                if !piece.is_empty() {
+                    words.last_mut().unwrap().0.push(Span { text: piece.clone(), ..span.clone() });
+                    piece.clear();
+                }
+                if !words.last().unwrap().0.is_empty() {
+                    words.push((Vec::new(), false));
+                }
+                if c == '\n' {
+                    words.last_mut().unwrap().1 = true;
+                }
+            } else {
+                piece.push(c);
+            }
+        }
+        if !piece.is_empty() {
+            words.last_mut().unwrap().0.push(Span { text: piece, ..span.clone() });
+        }
+    }
+
+    let space = text_width(" ", Style::Regular, size);
+    let mut lines: Vec<Vec<(f32, Span)>> = Vec::new();
+    let mut x = 0.;
+    for (pieces, breaks) in words.iter().filter(|(pieces, _)| !pieces.is_empty()) {
+        for (i, pieces) in break_word(pieces, size, width).into_iter().enumerate() {
+            let word_width = pieces
+                .iter()
+                .map(|p| text_width(&p.text, p.style, size))
+                .sum::<f32>();
+            // The rest of a broken word always starts a line.
+            if lines.is_empty() || *breaks || i > 0 || x + space + word_width > width {
+                lines.push(Vec::new());
+                x = 0.;
+            } else {
+                x += space;
+            }
+            for piece in pieces {
+                let piece_width = text_width(&piece.text, piece.style, size);
+                lines.last_mut().unwrap().push((x, piece));
+                x += piece_width;
+            }
+        }
+    }
+    lines
+}
+
+/// Splits the word made of `pieces` into parts that fit in `width`
+/// millimeters at `size` points, or leaves it whole when it already fits.
+fn break_word(pieces: &[Span], size: f32, width: f32) -> Vec<Vec<Span>> {
+    let word_width = pieces
+        .iter()
+        .map(|p| text_width(&p.text, p.style, size))
+        .sum::<f32>();
+    if word_width <= width {
+        return vec![pieces.to_vec()];
+    }
+
+    let mut parts = vec![Vec::<Span>::new()];
+    let mut x = 0.;
+    for piece in pieces {
+        let mut text = String::new();
+        for c in piece.text.chars() {
+            let c_width = text_width(c.encode_utf8(&mut [0; 4]), piece.style, size);
+            if x + c_width > width && x > 0. {
+                if !text.is_empty() {
+                    parts.last_mut().unwrap().push(Span { text: text.clone(), ..piece.clone() });
+                    text.clear();
+                }
+                parts.push(Vec::new());
+                x = 0.;
+            }
+            text.push(c);
+            x += c_width;
+        }
+        if !text.is_empty() {
+            parts.last_mut().unwrap().push(Span { text, ..piece.clone() });
+        }
+    }
+    parts
+}
+
+/// Struct that represents a PDF being written top to bottom, with the same
+/// typography for every document of the site: Helvetica on A4 pages, with
+/// the title and number of every page in its footer.
+pub(crate) struct PdfWriter {
+    title: String,
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    italic: IndirectFontRef,
+    code: IndirectFontRef,
+    /// Layers of every page, to write the footers to.
+    pages: Vec<PdfLayerReference>,
+    /// Height, from the bottom of the page, where the next line goes.
+    y: f32,
+}
+
+impl PdfWriter {
+    /// Starts a document with the metadata of its `title`, `author`,
+    /// `subject` and `keywords`, created on `date`.
+    pub(crate) fn new(
+        title: &str,
+        author: &str,
+        subject: &str,
+        keywords: &[String],
+        date: NaiveDate,
+    ) -> Result<Self, &'static str> {
+        let seconds = date
+            .signed_duration_since(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .num_seconds();
+        let date = match OffsetDateTime::from_unix_timestamp(seconds) {
+            Ok(d) => d,
+            _ => return Err("Couldn't convert PDF date."),
+        };
+
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Text");
+        let doc = doc
+            .with_author(author)
+            .with_subject(subject)
+            .with_keywords(keywords.to_vec())
+            .with_creator("personal-website")
+            .with_creation_date(date)
+            .with_mod_date(date)
+            .with_metadata_date(date);
+
+        let font = |font| match doc.add_builtin_font(font) {
+            Ok(f) => Ok(f),
+            _ => Err("Couldn't add PDF font."),
+        };
+        let regular = font(BuiltinFont::Helvetica)?;
+        let bold = font(BuiltinFont::HelveticaBold)?;
+        let italic = font(BuiltinFont::HelveticaOblique)?;
+        let code = font(BuiltinFont::Courier)?;
+
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self {
+            title: title.to_string(),
+            doc,
+            layer: layer.clone(),
+            regular,
+            bold,
+            italic,
+            code,
+            pages: vec![layer],
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn font(&self, style: Style) -> &IndirectFontRef {
+        match style {
+            Style::Regular => &self.regular,
+            Style::Bold => &self.bold,
+            Style::Italic => &self.italic,
+            Style::Code => &self.code,
+        }
+    }
+
+    /// Moves down by `height`, on a new page if the current one can't fit it.
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Text");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.pages.push(self.layer.clone());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    /// Leaves `height` millimeters blank, unless at the top of a page.
+    pub(crate) fn space(&mut self, height: f32) {
+        if self.y < PAGE_HEIGHT - MARGIN {
+            self.y -= height;
+        }
+    }
+
+    /// Draws `span` on `layer` from `x` along the baseline at `y`, returning
+    /// its width.
+    fn draw(&self, layer: &PdfLayerReference, y: f32, span: &Span, size: f32, x: f32) -> f32 {
+        let width = text_width(&span.text, span.style, size);
+        if let Some(url) = &span.link {
+            let (r, g, b) = LINK_COLOR;
+            layer.set_fill_color(Color::Rgb(Rgb::new(r, g, b, None)));
+            layer.add_link_annotation(LinkAnnotation::new(
+                Rect::new(
+                    Mm(x),
+                    Mm(y - size * POINT * 0.25),
+                    Mm(x + width),
+                    Mm(y + size * POINT * 0.8),
+                ),
+                Some(BorderArray::Solid([0., 0., 0.])),
+                Some(ColorArray::Transparent),
+                Actions::uri(url.clone()),
+                None,
+            ));
+        }
+        layer.use_text(span.text.as_str(), size, Mm(x), Mm(y), self.font(span.style));
+        if span.link.is_some() {
+            layer.set_fill_color(Color::Rgb(Rgb::new(0., 0., 0., None)));
+        }
+        width
+    }
+
+    /// Writes `spans` wrapped to the width of the page, `indent` millimeters
+    /// in from the margin. Returns the layer and baseline of the first line,
+    /// to write beside it.
+    pub(crate) fn text(&mut self, spans: &[Span], size: f32, indent: f32) -> (PdfLayerReference, f32) {
+        let left = MARGIN + indent;
+        let line_height = size * POINT * LINE_SPACING;
+
+        let mut first_line = None;
+        for line in lay_out(spans, size, PAGE_WIDTH - MARGIN - left) {
+            self.advance(line_height);
+            if first_line.is_none() {
+                first_line = Some((self.layer.clone(), self.y));
+            }
+            for (x, piece) in &line {
+                self.draw(&self.layer, self.y, piece, size, left + x);
+            }
+        }
+
+        match first_line {
+            Some(line) => line,
+            None => {
+                self.advance(line_height);
+                (self.layer.clone(), self.y)
+            }
+        }
+    }
+
+    pub(crate) fn heading(&mut self, level: usize, spans: &[Span]) {
+        let size = HEADING_SIZES[level.clamp(1, 6) - 1];
+        self.space(size * POINT * 0.6);
+        let spans = spans
+            .iter()
+            .map(|s| Span { style: Style::Bold, ..s.clone() })
+            .collect::<Vec<_>>();
+        self.text(&spans, size, 0.);
+        self.space(size * POINT * 0.3);
+    }
+
+    pub(crate) fn paragraph(&mut self, spans: &[Span]) {
+        self.text(spans, TEXT_SIZE, 0.);
+        self.space(TEXT_SIZE * POINT * 0.6);
+    }
+
+    /// Writes an item of a list, with its `marker` hanging in the indent.
+    pub(crate) fn item(&mut self, marker: &str, spans: &[Span]) {
+        let indent = 6.;
+        let (layer, y) = self.text(spans, TEXT_SIZE, indent);
+        let width = text_width(marker, Style::Regular, TEXT_SIZE);
+        self.draw(
+            &layer,
+            y,
+            &Span::new(marker, Style::Regular),
+            TEXT_SIZE,
+            MARGIN + indent - width - 1.5,
+        );
+        self.space(TEXT_SIZE * POINT * 0.3);
+    }
+
+    /// Writes `left` and `right` on the same line, `right` against the right
+    /// margin, such as the title and dates of an entry of the CV.
+    pub(crate) fn entry(&mut self, left: &[Span], right: &str) {
+        let right_width = text_width(right, Style::Regular, TEXT_SIZE);
+        let (layer, y) = self.text(left, TEXT_SIZE, 0.);
+        self.draw(
+            &layer,
+            y,
+            &Span::new(right, Style::Regular),
+            TEXT_SIZE,
+            PAGE_WIDTH - MARGIN - right_width,
+        );
+    }
+
+    /// Writes `code` as it is, in Courier, cutting lines that don't fit.
+    pub(crate) fn code(&mut self, code: &str) {
+        let width = text_width("m", Style::Code, CODE_SIZE);
+        let columns = ((PAGE_WIDTH - 2. * MARGIN) / width) as usize;
+        for line in code.lines() {
+            let line = line.chars().take(columns).collect::<String>();
+            self.advance(CODE_SIZE * POINT * LINE_SPACING);
+            self.draw(&self.layer, self.y, &Span::new(&line, Style::Code), CODE_SIZE, MARGIN);
+        }
+        self.space(TEXT_SIZE * POINT * 0.6);
+    }
+
+    pub(crate) fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Heading(level, spans) => self.heading(*level, spans),
+                Block::Paragraph(spans) => self.paragraph(spans),
+                Block::Item(marker, spans) => self.item(marker, spans),
+                Block::Code(code) => self.code(code),
+            }
+        }
+    }
+
+    /// Writes the footers, with the title and the number of every page, and
+    /// saves the document to `pdf_path`.
+    pub(crate) fn save(self, pdf_path: &str) -> Result<(), &'static str> {
+        let total = self.pages.len();
+        for (number, layer) in self.pages.iter().enumerate() {
+            layer.use_text(self.title.as_str(), FOOTER_SIZE, Mm(MARGIN), Mm(FOOTER), &self.regular);
+            let page = format!("{} / {}", number + 1, total);
+            let width = text_width(&page, Style::Regular, FOOTER_SIZE);
+            layer.use_text(
+                page,
+                FOOTER_SIZE,
+                Mm(PAGE_WIDTH - MARGIN - width),
+                Mm(FOOTER),
+                &self.regular,
+            );
+        }
+
+        let file = match File::create(pdf_path) {
+            Ok(f) => f,
+            _ => return Err("Couldn't create PDF file."),
+        };
+        match self.doc.save(&mut BufWriter::new(file)) {
+            Ok(_) => Ok(()),
+            _ => Err("Couldn't write PDF file."),
+        }
+    }
+}
+
+/// Splits the HTML that comrak renders into the blocks that PDFs are laid out
+/// from, with the targets of links passed through `resolve`. Only the text of
+/// the elements it knows is kept, in their style: images and raw HTML are
+/// left out.
+pub(crate) fn blocks_from_html(html: &str, resolve: &dyn Fn(&str) -> String) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    // Block being written, if any, and its spans.
+    let mut current: Option<Block> = None;
+    let mut bold = 0;
+    let mut italic = 0;
+    let mut code = 0;
+    let mut link: Option<String> = None;
+    let mut pre = false;
+    // Number of the next item of every open list, or none when unordered.
+    let mut lists: Vec<Option<usize>> = Vec::new();
+
+    fn spans_of(block: &mut Option<Block>) -> Option<&mut Vec<Span>> {
+        match block {
+            Some(Block::Heading(_, spans)) | Some(Block::Paragraph(spans)) | Some(Block::Item(_, spans)) => Some(spans),
+            _ => None,
+        }
+    }
+    fn flush(current: &mut Option<Block>, blocks: &mut Vec<Block>) {
+        if let Some(block) = current.take() {
+            let empty = match &block {
+                Block::Heading(_, spans) | Block::Paragraph(spans) | Block::Item(_, spans) => {
+                    spans.iter().all(|s| s.text.trim().is_empty())
+                }
+                Block::Code(code) => code.is_empty(),
+            };
+            if !empty {
+                blocks.push(block);
+            }
+        }
+    }
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (text, tag) = match rest.find('<') {
+            Some(0) => match rest.find('>') {
+                Some(end) => {
+                    let tag = &rest[1..end];
+                    rest = &rest[end + 1..];
+                    ("", Some(tag))
+                }
+                None => {
+                    let text = rest;
+                    rest = "";
+                    (text, None)
+                }
+            },
+            Some(start) => {
+                let text = &rest[..start];
+                rest = &rest[start..];
+                (text, None)
+            }
+            None => {
+                let text = rest;
+                rest = "";
+                (text, None)
+            }
+        };
+
+        if !text.is_empty() {
+            let text = unescape_html(text);
+            if pre {
+                if let Some(Block::Code(code)) = &mut current {
+                    code.push_str(&text);
+                }
+                continue;
+            }
+            let text = text.replace('\n', " ");
+            if current.is_none() {
+                if text.trim().is_empty() {
+                    continue;
+                }
+                current = Some(Block::Paragraph(Vec::new()));
+            }
+            let style = if code > 0 {
+                Style::Code
+            } else if bold > 0 {
+                Style::Bold
+            } else if italic > 0 {
+                Style::Italic
+            } else {
+                Style::Regular
+            };
+            if let Some(spans) = spans_of(&mut current) {
+                spans.push(Span {
+                    text,
+                    style,
+                    link: link.clone(),
+                });
+            }
+            continue;
+        }
+
+        let tag = match tag {
+            Some(t) => t,
+            None => continue,
+        };
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        match (name.as_str(), closing) {
+            ("p", false) => {
+                let in_empty_item = matches!(&current, Some(Block::Item(_, spans)) if spans.is_empty());
+                if !in_empty_item {
+                    flush(&mut current, &mut blocks);
+                    current = Some(Block::Paragraph(Vec::new()));
+                }
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                flush(&mut current, &mut blocks);
+                let level = name[1..].parse().unwrap_or(1);
+                current = Some(Block::Heading(level, Vec::new()));
+            }
+            ("ul", false) => lists.push(None),
+            ("ol", false) => {
+                let start = tag
+                    .split_once("start=\"")
+                    .and_then(|(_, s)| s.split('"').next()?.parse().ok())
+                    .unwrap_or(1);
+                lists.push(Some(start));
+            }
+            ("ul" | "ol", true) => {
+                flush(&mut current, &mut blocks);
+                lists.pop();
+            }
+            ("li", false) => {
+                flush(&mut current, &mut blocks);
+                let marker = match lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => String::from("•"),
+                };
+                current = Some(Block::Item(marker, Vec::new()));
+            }
+            ("pre", false) => {
+                flush(&mut current, &mut blocks);
+                current = Some(Block::Code(String::new()));
+                pre = true;
+            }
+            ("pre", true) => {
+                pre = false;
+                flush(&mut current, &mut blocks);
+            }
+            ("p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "li" | "blockquote" | "tr", true) => {
+                flush(&mut current, &mut blocks)
+            }
+            ("br", _) => {
+                if let Some(spans) = spans_of(&mut current) {
+                    spans.push(Span::new("\n", Style::Regular));
+                }
+            }
+            ("td" | "th", true) => {
+                if let Some(spans) = spans_of(&mut current) {
+                    spans.push(Span::new("  ", Style::Regular));
+                }
+            }
+            ("strong" | "b", false) => bold += 1,
+            ("strong" | "b", true) => bold -= 1,
+            ("em" | "i", false) => italic += 1,
+            ("em" | "i", true) => italic -= 1,
+            ("code", false) => code += 1,
+            ("code", true) => code -= 1,
+            ("a", false) => {
+                link = tag
+                    .split_once("href=\"")
+                    .and_then(|(_, href)| href.split('"').next())
+                    .map(|href| resolve(&unescape_html(href)));
+            }
+            ("a", true) => link = None,
+            _ => (),
+        }
+    }
+    flush(&mut current, &mut blocks);
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text of `spans`, with the style of every piece.
+    fn styled(spans: &[Span]) -> Vec<(&str, Style)> {
+        spans.iter().map(|s| (s.text.as_str(), s.style)).collect()
+    }
+
+    #[test]
+    fn blocks_from_comrak_html() {
+        let html = "<h2>Results</h2>\n\
+            <p>Some <strong>bold <em>and italic</em></strong> and <em>italic</em> text, with <code>code</code>.</p>\n\
+            <ol start=\"3\">\n<li>Third</li>\n<li><p>Fourth</p></li>\n</ol>\n\
+            <ul>\n<li>Bullet</li>\n</ul>\n\
+            <pre><code class=\"language-rust\">fn main() {\n    println!(&quot;&lt;hi&gt;&quot;);\n}\n</code></pre>\n\
+            <p>See <a href=\"@fig:chart\">the chart</a>.</p>\n";
+        let resolve = |href: &str| href.replace("@fig:", "study.html#");
+        let blocks = blocks_from_html(html, &resolve);
+        assert_eq!(blocks.len(), 7);
+
+        match &blocks[0] {
+            Block::Heading(level, spans) => {
+                assert_eq!(*level, 2);
+                assert_eq!(styled(spans), [("Results", Style::Regular)]);
+            }
+            block => panic!("Expected a heading, got {:?}", block),
+        }
+
+        match &blocks[1] {
+            Block::Paragraph(spans) => assert_eq!(
+                styled(spans),
+                [
+                    ("Some ", Style::Regular),
+                    ("bold ", Style::Bold),
+                    ("and italic", Style::Bold),
+                    (" and ", Style::Regular),
+                    ("italic", Style::Italic),
+                    (" text, with ", Style::Regular),
+                    ("code", Style::Code),
+                    (".", Style::Regular),
+                ]
+            ),
+            block => panic!("Expected a paragraph, got {:?}", block),
+        }
+
+        let items = blocks[2..5]
+            .iter()
+            .map(|block| match block {
+                Block::Item(marker, spans) => (marker.as_str(), styled(spans)[0].0),
+                block => panic!("Expected an item, got {:?}", block),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(items, [("3.", "Third"), ("4.", "Fourth"), ("•", "Bullet")]);
+
+        match &blocks[5] {
+            Block::Code(code) => assert_eq!(code, "fn main() {\n    println!(\"<hi>\");\n}\n"),
+            block => panic!("Expected code, got {:?}", block),
+        }
+
+        match &blocks[6] {
+            Block::Paragraph(spans) => {
+                assert_eq!(spans[1].text, "the chart");
+                assert_eq!(spans[1].link.as_deref(), Some("study.html#chart"));
+                assert!(spans[0].link.is_none() && spans[2].link.is_none());
+            }
+            block => panic!("Expected a paragraph, got {:?}", block),
+        }
+    }
+
+    #[test]
+    fn text_wraps_to_the_margins() {
+        let width = PAGE_WIDTH - 2. * MARGIN;
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(12);
+        let spans = [
+            Span::new(&text, Style::Regular),
+            Span::new("Bold words at the end.", Style::Bold),
+        ];
+        let lines = lay_out(&spans, TEXT_SIZE, width);
+        assert!(lines.len() > 3);
+        for line in &lines {
+            let (x, last) = line.last().unwrap();
+            assert!(x + text_width(&last.text, last.style, TEXT_SIZE) <= width);
+            assert_eq!(line[0].0, 0.);
+        }
+        // Every word is kept, in order.
+        let words = lines.iter().flatten().map(|(_, p)| p.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(words.join(" "), format!("{}Bold words at the end.", text));
+
+        // Line breaks of the spans start a line.
+        let lines = lay_out(&[Span::new("One\ntwo", Style::Regular)], TEXT_SIZE, width);
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn text_breaks_words_wider_than_a_line() {
+        let width = 40.;
+        let url = "https://example.com/".repeat(8);
+        let spans = [Span::new("See", Style::Regular), Span::link(&format!(" {} now", url), "https://example.com")];
+        let lines = lay_out(&spans, TEXT_SIZE, width);
+        for line in &lines {
+            for (x, piece) in line {
+                assert!(*x >= 0.);
+                assert!(x + text_width(&piece.text, piece.style, TEXT_SIZE) <= width);
+            }
+        }
+        let pieces = lines.iter().flatten().map(|(_, p)| p.text.as_str()).collect::<String>();
+        assert_eq!(pieces, format!("See{}now", url));
+        assert!(lines.iter().flatten().filter(|(_, p)| p.text != "See").all(|(_, p)| p.link.is_some()));
+    }
+}
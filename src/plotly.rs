@@ -0,0 +1,313 @@
+use serde_json::{Deserializer, Value};
+
+use super::chart::{BarSeries, Chart, ChartData, TreeNode};
+use super::table::DataTable;
+
+/// Arguments of the `Plotly.newPlot` call of `data_script`, which are the id
+/// of the div, the traces, the layout and the config, all JSON values
+/// separated by commas.
+pub(crate) fn plot_arguments(data_script: &str) -> Result<Vec<Value>, &'static str> {
+    let mut rest = match data_script.split_once("Plotly.newPlot(") {
+        Some((_, args)) => args,
+        _ => return Err("Couldn't find Plotly.newPlot call."),
+    };
+
+    let mut values = Vec::new();
+    while values.len() < 4 {
+        rest = rest.trim_start().trim_start_matches(',').trim_start();
+        if rest.starts_with(')') {
+            break;
+        }
+        let mut stream = Deserializer::from_str(rest).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(value)) => values.push(value),
+            _ => return Err("Couldn't parse Plotly.newPlot arguments."),
+        }
+        rest = &rest[stream.byte_offset()..];
+    }
+    Ok(values)
+}
+
+/// Struct that represents the arguments of the `Plotly.newPlot` call of a
+/// figure exported by Plotly.
+#[derive(Debug)]
+pub(crate) struct PlotlyPlot {
+    pub(crate) traces: Vec<Value>,
+    pub(crate) layout: Value,
+}
+
+impl PlotlyPlot {
+    pub(crate) fn from_data_script(data_script: &str) -> Result<Self, &'static str> {
+        let mut values = plot_arguments(data_script)?;
+        if values.len() < 3 {
+            return Err("Couldn't parse Plotly.newPlot arguments.");
+        }
+
+        let layout = values.remove(2);
+        match values.remove(1) {
+            Value::Array(traces) => Ok(Self { traces, layout }),
+            _ => Err("Couldn't find Plotly traces."),
+        }
+    }
+
+    fn axis_title(&self, axis: &str) -> String {
+        let title = match &self.layout[axis]["title"] {
+            Value::String(s) => s.as_str(),
+            title => title["text"].as_str().unwrap_or(""),
+        };
+        // Titles may be LaTeX, e.g. `$S_S$`, which SVG can't typeset.
+        title.replace(['$', '_'], "")
+    }
+
+    /// Lays out the data of every trace as rows of a table, one per point,
+    /// with the name of the trace as the first column.
+    pub(crate) fn to_data_table(&self) -> DataTable {
+        let keys = ["ids", "parents", "labels", "x", "y", "values", "text"]
+            .into_iter()
+            .filter(|key| self.traces.iter().any(|t| t[*key].is_array()))
+            .collect::<Vec<_>>();
+
+        let mut rows = Vec::new();
+        for trace in &self.traces {
+            let length = keys.iter().map(|key| array(&trace[*key]).len()).max().unwrap_or(0);
+            for i in 0..length {
+                rows.push(
+                    std::iter::once(text(&trace["name"]))
+                        .chain(keys.iter().map(|key| array(&trace[*key]).get(i).map(text).unwrap_or_default()))
+                        .collect(),
+                );
+            }
+        }
+
+        DataTable::from_columns(
+            std::iter::once("trace").chain(keys).map(String::from).collect(),
+            rows,
+        )
+    }
+
+    /// Converts the traces to a `Chart`, as long as they are all of the
+    /// same family: bar, box/violin, scatter or treemap/icicle.
+    pub(crate) fn to_chart(&self) -> Result<Chart, &'static str> {
+        let kind = match self.traces.first().and_then(|t| t["type"].as_str()) {
+            Some(k) => k,
+            _ => return Err("Couldn't find type of Plotly trace."),
+        };
+        let horizontal = self.traces[0]["orientation"] == "h";
+        let (category_key, value_key) = if horizontal { ("y", "x") } else { ("x", "y") };
+        let (category_axis, value_axis) = if horizontal {
+            ("yaxis", "xaxis")
+        } else {
+            ("xaxis", "yaxis")
+        };
+
+        let (data, x_title, y_title) = match kind {
+            "bar" => {
+                let mut categories = Vec::<String>::new();
+                for trace in &self.traces {
+                    for category in array(&trace[category_key]).iter().map(text) {
+                        if !categories.contains(&category) {
+                            categories.push(category);
+                        }
+                    }
+                }
+
+                let series = self
+                    .traces
+                    .iter()
+                    .map(|trace| {
+                        let mut values = vec![0.; categories.len()];
+                        let pairs = array(&trace[category_key]).iter().zip(array(&trace[value_key]));
+                        for (category, value) in pairs {
+                            let c = categories.iter().position(|c| *c == text(category)).unwrap();
+                            values[c] += value.as_f64().unwrap_or(0.);
+                        }
+                        BarSeries {
+                            name: text(&trace["name"]),
+                            values,
+                        }
+                    })
+                    .collect();
+
+                let data = ChartData::Bar {
+                    categories,
+                    series,
+                    stacked: matches!(self.layout["barmode"].as_str(), Some("stack" | "relative")),
+                };
+                (data, self.axis_title(category_axis), self.axis_title(value_axis))
+            }
+            "box" | "violin" => {
+                // Values are on the axis that holds numbers; the other axis,
+                // when present, holds the group of each value.
+                let (category_key, value_key) = if self.traces[0]["y"].is_null()
+                    || self.traces[0]["y"][0].is_string()
+                {
+                    ("y", "x")
+                } else {
+                    ("x", "y")
+                };
+
+                let mut groups = Vec::<(String, Vec<f64>)>::new();
+                for trace in &self.traces {
+                    let categories = array(&trace[category_key]);
+                    for (i, value) in array(&trace[value_key]).iter().enumerate() {
+                        let group = match categories.get(i) {
+                            Some(c) => text(c),
+                            None => text(&trace["name"]),
+                        };
+                        let g = match groups.iter().position(|(g, _)| *g == group) {
+                            Some(g) => g,
+                            None => {
+                                groups.push((group, Vec::new()));
+                                groups.len() - 1
+                            }
+                        };
+                        if let Some(v) = value.as_f64() {
+                            groups[g].1.push(v);
+                        }
+                    }
+                }
+
+                let (category_axis, value_axis) = if category_key == "y" {
+                    ("yaxis", "xaxis")
+                } else {
+                    ("xaxis", "yaxis")
+                };
+                let data = ChartData::Violin { groups };
+                (data, self.axis_title(category_axis), self.axis_title(value_axis))
+            }
+            "scatter" | "scattergl" => {
+                let series = self
+                    .traces
+                    .iter()
+                    .map(|trace| {
+                        let points = array(&trace["x"])
+                            .iter()
+                            .zip(array(&trace["y"]))
+                            .filter_map(|(x, y)| Some((x.as_f64()?, y.as_f64()?)))
+                            .collect();
+                        (text(&trace["name"]), points)
+                    })
+                    .collect();
+
+                let data = ChartData::Scatter { series };
+                (data, self.axis_title("xaxis"), self.axis_title("yaxis"))
+            }
+            "treemap" | "icicle" => {
+                let trace = &self.traces[0];
+                let labels = array(&trace["labels"]);
+                let ids = match trace["ids"].as_array() {
+                    Some(ids) => ids.iter().map(text).collect::<Vec<_>>(),
+                    None => labels.iter().map(text).collect(),
+                };
+                let parents = array(&trace["parents"]).iter().map(text).collect::<Vec<_>>();
+                let values = array(&trace["values"]);
+
+                let roots = (0..ids.len())
+                    .filter(|&i| parents.get(i).is_none_or(|p| p.is_empty() || !ids.contains(p)))
+                    .map(|i| tree_node(i, &ids, labels, &parents, values))
+                    .collect();
+
+                (ChartData::Treemap { roots }, String::new(), String::new())
+            }
+            _ => return Err("Unsupported type of Plotly trace."),
+        };
+
+        Ok(Chart {
+            data,
+            x_title,
+            y_title,
+        })
+    }
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(|a| a.as_slice()).unwrap_or(&[])
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        v => v.to_string(),
+    }
+}
+
+/// Builds the node at `i` with its descendants. Nodes without a value are
+/// worth the sum of their children, or one when they are leaves.
+fn tree_node(i: usize, ids: &[String], labels: &[Value], parents: &[String], values: &[Value]) -> TreeNode {
+    let children = (0..ids.len())
+        .filter(|&c| parents.get(c) == Some(&ids[i]))
+        .map(|c| tree_node(c, ids, labels, parents, values))
+        .collect::<Vec<_>>();
+
+    let value = match values.get(i).and_then(|v| v.as_f64()) {
+        Some(v) => v,
+        None if children.is_empty() => 1.,
+        None => children.iter().map(|c| c.value).sum(),
+    };
+
+    TreeNode {
+        label: labels.get(i).map(text).unwrap_or_else(|| ids[i].clone()),
+        value,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Figure;
+
+    #[test]
+    fn plotly_plot_from_figure() {
+        let figure = Figure::from_file("sms_figures/individual_D050_to_D053_treemap.html").unwrap();
+        let plot = PlotlyPlot::from_data_script(&figure.data_script).unwrap();
+        assert_eq!(plot.traces[0]["type"], "treemap");
+        assert!(plot.to_chart().is_ok());
+    }
+
+    #[test]
+    fn chart_summary_from_figure() {
+        let figure = Figure::from_file("sms_figures/pair_ss00_d010.html").unwrap();
+        let chart = PlotlyPlot::from_data_script(&figure.data_script)
+            .and_then(|p| p.to_chart())
+            .unwrap();
+        assert!(chart.label().starts_with("Violin plot"));
+        assert!(chart.summary().contains("median"));
+        assert!(chart.to_data_table().to_csv().starts_with("Type of Approach,Values,Min,Median,Max"));
+    }
+
+    #[test]
+    fn plot_from_data_script() {
+        let script = r##"window.PLOTLYENV=window.PLOTLYENV || {};
+            if (document.getElementById("id")) {
+                Plotly.newPlot("id", [{"type": "bar", "name": "A", "x": ["2021", "2022"], "y": [29, 5]},
+                    {"type": "bar", "name": "B", "x": ["2022"], "y": [-2]}],
+                    {"barmode": "relative", "xaxis": {"title": {"text": "$S_S$"}}, "yaxis": {"title": "#"}},
+                    {"responsive": true})
+            };"##;
+        assert_eq!(plot_arguments(script).unwrap().len(), 4);
+        assert!(plot_arguments("Plotly.newPlot(\"id\", [)").is_err());
+        assert!(PlotlyPlot::from_data_script("Plotly.newPlot(\"id\")").is_err());
+
+        let plot = PlotlyPlot::from_data_script(script).unwrap();
+        assert_eq!(plot.axis_title("xaxis"), "SS");
+        assert_eq!(plot.to_data_table().to_csv(), "trace,x,y\nA,2021,29\nA,2022,5\nB,2022,-2\n");
+
+        let chart = plot.to_chart().unwrap();
+        assert_eq!((chart.x_title.as_str(), chart.y_title.as_str()), ("SS", "#"));
+        match chart.data {
+            ChartData::Bar {
+                categories,
+                series,
+                stacked,
+            } => {
+                assert_eq!(categories, ["2021", "2022"]);
+                assert_eq!(series[1].values, [0., -2.]);
+                assert!(stacked);
+            }
+            data => panic!("Expected bars, got {:?}", data),
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use super::images::{Credit, Overlay};
+
+/// Struct that represents the settings of the build, as described by the
+/// `site.toml` file. Every setting has a default, so the file is optional.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SiteConfig {
+    /// Address the site is published at, which links shared elsewhere need
+    /// to be absolute.
+    pub(crate) url: Option<String>,
+    #[serde(default)]
+    pub(crate) overlay: OverlayConfig,
+    #[serde(default)]
+    pub(crate) compression: CompressionConfig,
+    #[serde(default)]
+    pub(crate) backgrounds: BackgroundsConfig,
+    #[serde(default)]
+    pub(crate) gallery: GalleryConfig,
+    #[serde(default)]
+    pub(crate) icons: IconsConfig,
+    #[serde(default)]
+    pub(crate) manifest: ManifestConfig,
+    #[serde(default)]
+    pub(crate) offline: OfflineConfig,
+    #[serde(default)]
+    pub(crate) publications: ListingConfig,
+    #[serde(default)]
+    pub(crate) miscellaneous: ListingConfig,
+    #[serde(default)]
+    pub(crate) homepage: HomepageConfig,
+    #[serde(default)]
+    pub(crate) pdf: PdfConfig,
+    #[serde(default)]
+    pub(crate) highlight: HighlightConfig,
+    /// When the file was last modified, so that stages can tell whether
+    /// their outputs were made with the current settings.
+    #[serde(skip)]
+    pub(crate) modified: Option<SystemTime>,
+}
+
+/// Overlays applied to the background images in `source`, so that text
+/// stays readable on top of them: `light` for the default theme, and `dark`
+/// for readers who prefer a dark one.
+#[derive(Debug, Deserialize)]
+pub(crate) struct OverlayConfig {
+    pub(crate) source: String,
+    pub(crate) light: Overlay,
+    pub(crate) dark: Option<Overlay>,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            source: String::from("img"),
+            light: Overlay::default(),
+            dark: None,
+        }
+    }
+}
+
+/// JPEG `quality` and `size_ratio` the light backgrounds are compressed to
+/// `dest` with, which `overrides` can change for single images, by file name.
+#[derive(Debug, Deserialize)]
+pub(crate) struct CompressionConfig {
+    #[serde(default = "default_compression_dest")]
+    pub(crate) dest: String,
+    #[serde(default = "default_quality")]
+    pub(crate) quality: f32,
+    #[serde(default = "default_size_ratio")]
+    pub(crate) size_ratio: f32,
+    #[serde(default)]
+    pub(crate) overrides: HashMap<String, CompressionOverride>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CompressionOverride {
+    pub(crate) quality: Option<f32>,
+    pub(crate) size_ratio: Option<f32>,
+}
+
+fn default_compression_dest() -> String {
+    String::from("compressed-img")
+}
+
+fn default_quality() -> f32 {
+    69.
+}
+
+fn default_size_ratio() -> f32 {
+    1.
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            dest: default_compression_dest(),
+            quality: default_quality(),
+            size_ratio: default_size_ratio(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Quality and size ratio of the image called `file_name`.
+    pub(crate) fn factor(&self, file_name: &str) -> Result<(f32, f32), &'static str> {
+        let (quality, size_ratio) = match self.overrides.get(file_name) {
+            Some(o) => (
+                o.quality.unwrap_or(self.quality),
+                o.size_ratio.unwrap_or(self.size_ratio),
+            ),
+            None => (self.quality, self.size_ratio),
+        };
+        if quality <= 0. || quality > 100. {
+            return Err("Couldn't use compression quality outside of (0, 100].");
+        }
+        if size_ratio <= 0. || size_ratio > 1. {
+            return Err("Couldn't use compression size ratio outside of (0, 1].");
+        }
+        Ok((quality, size_ratio))
+    }
+}
+
+/// Backgrounds of the pages, which are the images of the overlay `source`
+/// but those in `exclude`, and the `credits` of each of them, by file name.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BackgroundsConfig {
+    #[serde(default = "default_exclude")]
+    pub(crate) exclude: Vec<String>,
+    #[serde(default)]
+    pub(crate) credits: HashMap<String, Credit>,
+}
+
+fn default_exclude() -> Vec<String> {
+    vec![String::from("blank.jpg")]
+}
+
+impl Default for BackgroundsConfig {
+    fn default() -> Self {
+        Self {
+            exclude: default_exclude(),
+            credits: HashMap::new(),
+        }
+    }
+}
+
+/// Photos of the gallery, which are the images in `source` but those in
+/// `exclude`, with the titles and captions of the `captions` file.
+#[derive(Debug, Deserialize)]
+pub(crate) struct GalleryConfig {
+    #[serde(default = "default_gallery_source")]
+    pub(crate) source: String,
+    #[serde(default = "default_gallery_captions")]
+    pub(crate) captions: String,
+    #[serde(default = "default_exclude")]
+    pub(crate) exclude: Vec<String>,
+}
+
+fn default_gallery_source() -> String {
+    String::from("img")
+}
+
+fn default_gallery_captions() -> String {
+    String::from("img/captions.toml")
+}
+
+impl Default for GalleryConfig {
+    fn default() -> Self {
+        Self {
+            source: default_gallery_source(),
+            captions: default_gallery_captions(),
+            exclude: default_exclude(),
+        }
+    }
+}
+
+/// Icons of the site, made from the image at `source`, which is cropped to
+/// a circle when `circular`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct IconsConfig {
+    #[serde(default = "default_icons_source")]
+    pub(crate) source: String,
+    #[serde(default)]
+    pub(crate) circular: bool,
+}
+
+fn default_icons_source() -> String {
+    String::from("profile_pic.png")
+}
+
+impl Default for IconsConfig {
+    fn default() -> Self {
+        Self {
+            source: default_icons_source(),
+            circular: false,
+        }
+    }
+}
+
+/// What browsers show of the site when it is installed as an app, as
+/// written to its web app manifest.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ManifestConfig {
+    #[serde(default = "default_manifest_name")]
+    pub(crate) name: String,
+    #[serde(default = "default_manifest_name")]
+    pub(crate) short_name: String,
+    #[serde(default = "default_theme_color")]
+    pub(crate) theme_color: String,
+    #[serde(default = "default_background_color")]
+    pub(crate) background_color: String,
+}
+
+fn default_manifest_name() -> String {
+    String::from("Cláudio Gomes")
+}
+
+fn default_theme_color() -> String {
+    String::from("#92D4AB")
+}
+
+fn default_background_color() -> String {
+    String::from("#EBE9E4")
+}
+
+impl Default for ManifestConfig {
+    fn default() -> Self {
+        Self {
+            name: default_manifest_name(),
+            short_name: default_manifest_name(),
+            theme_color: default_theme_color(),
+            background_color: default_background_color(),
+        }
+    }
+}
+
+/// Whether pages register a service worker that keeps them readable
+/// offline, once visited.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct OfflineConfig {
+    #[serde(default)]
+    pub(crate) enabled: bool,
+}
+
+/// How a listing of posts is split into pages of `page_size` posts, and the
+/// tags that tell the type of a post, of which the first a post has is its
+/// type.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ListingConfig {
+    #[serde(default = "default_page_size")]
+    pub(crate) page_size: usize,
+    #[serde(default = "default_publication_types")]
+    pub(crate) types: Vec<String>,
+}
+
+fn default_page_size() -> usize {
+    10
+}
+
+fn default_publication_types() -> Vec<String> {
+    vec![String::from("thesis"), String::from("paper")]
+}
+
+impl Default for ListingConfig {
+    fn default() -> Self {
+        Self {
+            page_size: default_page_size(),
+            types: default_publication_types(),
+        }
+    }
+}
+
+/// How many of the latest publications and miscellaneous posts the homepage
+/// shows, with excerpts of up to `excerpt_length` characters.
+#[derive(Debug, Deserialize)]
+pub(crate) struct HomepageConfig {
+    #[serde(default = "default_homepage_latest")]
+    pub(crate) latest: usize,
+    #[serde(default = "default_excerpt_length")]
+    pub(crate) excerpt_length: usize,
+}
+
+fn default_homepage_latest() -> usize {
+    2
+}
+
+fn default_excerpt_length() -> usize {
+    280
+}
+
+impl Default for HomepageConfig {
+    fn default() -> Self {
+        Self {
+            latest: default_homepage_latest(),
+            excerpt_length: default_excerpt_length(),
+        }
+    }
+}
+
+/// PDFs the build writes besides `cv.pdf`: one of every publication, in
+/// `publications/<slug>.pdf`, when `publications` is set.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct PdfConfig {
+    #[serde(default)]
+    pub(crate) publications: bool,
+}
+
+/// How code blocks in the publications are highlighted: only those in one of
+/// `languages` are, either with classes styled by the `light` and `dark`
+/// themes, or with the colors of the `light` theme inlined.
+#[derive(Debug, Deserialize)]
+pub(crate) struct HighlightConfig {
+    #[serde(default = "default_highlight_languages")]
+    pub(crate) languages: Vec<String>,
+    #[serde(default)]
+    pub(crate) style: HighlightStyle,
+    #[serde(default = "default_highlight_light")]
+    pub(crate) light: String,
+    #[serde(default = "default_highlight_dark")]
+    pub(crate) dark: String,
+}
+
+/// Enum to enumerate how highlighted code is colored:
+/// `Classes` marks it with classes that `css/highlight.css` colors;
+/// `Inline` colors it with `style` attributes, without the dark theme.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum HighlightStyle {
+    #[default]
+    Classes,
+    Inline,
+}
+
+fn default_highlight_languages() -> Vec<String> {
+    ["rust", "python", "qsharp", "openqasm", "bash"]
+        .iter()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+fn default_highlight_light() -> String {
+    String::from("InspiredGitHub")
+}
+
+fn default_highlight_dark() -> String {
+    String::from("base16-ocean.dark")
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            languages: default_highlight_languages(),
+            style: HighlightStyle::default(),
+            light: default_highlight_light(),
+            dark: default_highlight_dark(),
+        }
+    }
+}
+
+impl SiteConfig {
+    pub(crate) fn from_file(config_path: &str) -> Result<Self, &'static str> {
+        if !Path::new(config_path).exists() {
+            return Ok(Self::default());
+        }
+
+        println!("Reading site configuration {:?}", config_path);
+        let config = match fs::read_to_string(config_path) {
+            Ok(c) => c,
+            _ => return Err("Couldn't read site configuration."),
+        };
+
+        let mut site_config: Self = match toml::from_str(&config) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("{}", e);
+                return Err("Couldn't parse site configuration.");
+            }
+        };
+        site_config.modified = fs::metadata(config_path).and_then(|m| m.modified()).ok();
+
+        Ok(site_config)
+    }
+
+    /// Makes `path` absolute when the address of the site is known.
+    pub(crate) fn absolute_url(&self, path: &str) -> String {
+        match &self.url {
+            Some(url) => format!("{}/{}", url.trim_end_matches('/'), path),
+            None => path.to_string(),
+        }
+    }
+}
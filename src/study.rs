@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::fs::File;
+use std::io::Write as OtherWrite;
+use std::path::Path;
+
+use html_builder::*;
+
+use serde::Deserialize;
+
+use sha2::{Digest, Sha256};
+
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use super::chart::ChartSpec;
+use super::site::SiteConfig;
+use super::table::{strip_tags, DataTable};
+use super::{
+    add_chart_to_node, add_figure_to_node, titlefy_to_page_title, vendor_plotly_runtime, FigureLoading, Page,
+    TopBarButton, CSS, FOLDER_DATA,
+};
+
+/// Struct that represents the companion pages of a research study, as
+/// described by a `study.toml` manifest.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Study {
+    title: String,
+    data_link: Option<String>,
+    #[serde(default)]
+    loading: FigureLoading,
+    #[serde(default)]
+    links: Vec<StudyLink>,
+    pages: Vec<StudyPage>,
+}
+
+/// External link added to the top bar after the pages of the study.
+#[derive(Debug, Deserialize)]
+struct StudyLink {
+    label: String,
+    icon: String,
+    href: String,
+}
+
+/// Struct that represents a page of a study, which is published to `file`.
+#[derive(Debug, Deserialize)]
+struct StudyPage {
+    file: String,
+    nav_title: String,
+    icon: String,
+    heading: String,
+    about: String,
+    #[serde(default)]
+    sections: Vec<StudySection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StudySection {
+    title: String,
+    #[serde(default)]
+    figures: Vec<StudyFigure>,
+    #[serde(default)]
+    tables: Vec<StudyTable>,
+}
+
+/// Figure of a section, which is either a Plotly figure exported to `path`
+/// or a `chart` rendered from data at build time.
+#[derive(Debug, Deserialize)]
+struct StudyFigure {
+    id: String,
+    path: Option<String>,
+    chart: Option<ChartSpec>,
+    caption: String,
+    #[serde(default)]
+    size: FigureSize,
+    /// How the figure is loaded, when it isn't how the study loads them.
+    loading: Option<FigureLoading>,
+}
+
+/// Table of a section, read from a pandas HTML dump, a CSV or a JSON file.
+#[derive(Debug, Deserialize)]
+struct StudyTable {
+    id: String,
+    path: String,
+    caption: String,
+}
+
+/// Enum to enumerate the sizes of a figure container:
+/// `Square` takes most of the screen height;
+/// `DoubleSize` takes twice as much, for figures with many groups.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum FigureSize {
+    #[default]
+    Square,
+    DoubleSize,
+}
+
+impl FigureSize {
+    fn class(&self) -> &'static str {
+        match self {
+            FigureSize::Square => "blank-container-square",
+            FigureSize::DoubleSize => "blank-container-square-double-size",
+        }
+    }
+}
+
+/// Struct that maps the id of every figure to the page it is published in and
+/// its number in that page, so that `@fig:<id>` references can be resolved.
+#[derive(Debug, Default)]
+pub(crate) struct FigureRefs {
+    figures: HashMap<String, (String, usize)>,
+}
+
+impl FigureRefs {
+    /// Replaces every `@fig:<id>` reference with an HTML link to the figure.
+    pub(crate) fn resolve_html(&self, text: &str) -> String {
+        self.resolve(text, |label, href| format!("<a href='{}'>{}</a>", href, label))
+    }
+
+    /// Replaces every `@fig:<id>` reference with a markdown link to the figure.
+    pub(crate) fn resolve_markdown(&self, text: &str) -> String {
+        self.resolve(text, |label, href| format!("[{}]({})", label, href))
+    }
+
+    /// Replaces every `@fig:<id>` reference with the label of the figure.
+    fn resolve_plain(&self, text: &str) -> String {
+        self.resolve(text, |label, _href| label.to_string())
+    }
+
+    fn resolve(&self, text: &str, link: impl Fn(&str, &str) -> String) -> String {
+        let mut resolved = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("@fig:") {
+            resolved.push_str(&rest[..start]);
+            let reference = &rest[start + "@fig:".len()..];
+            let end = reference
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(reference.len());
+            let id = &reference[..end];
+
+            match self.figures.get(id) {
+                Some((file, number)) => resolved.push_str(&link(
+                    &format!("Figure {}", number),
+                    &format!("{}#{}", file, id),
+                )),
+                None => {
+                    println!("Couldn't resolve reference to figure {:?}.", id);
+                    resolved.push_str(&rest[start..start + "@fig:".len() + end]);
+                }
+            }
+
+            rest = &reference[end..];
+        }
+        resolved.push_str(rest);
+        resolved
+    }
+}
+
+impl Study {
+    pub(crate) fn from_studies_in_folder(folder_path: &str) -> Result<Vec<Self>, &'static str> {
+        let paths = match fs::read_dir(folder_path) {
+            Ok(p) => p,
+            _ => return Err("Couldn't open folder."),
+        };
+
+        let mut studies = Vec::<Self>::new();
+
+        for path in paths {
+            let manifest_path = path.unwrap().path().join("study.toml");
+            if manifest_path.exists() {
+                studies.push(Self::from_file(&format!("{}", manifest_path.display()))?);
+            }
+        }
+
+        Ok(studies)
+    }
+
+    pub(crate) fn from_file(manifest_path: &str) -> Result<Self, &'static str> {
+        println!("Reading study manifest {:?}", manifest_path);
+        let manifest = match fs::read_to_string(manifest_path) {
+            Ok(m) => m,
+            _ => return Err("Couldn't read study manifest."),
+        };
+
+        match toml::from_str(&manifest) {
+            Ok(study) => Ok(study),
+            Err(e) => {
+                println!("{}", e);
+                Err("Couldn't parse study manifest.")
+            }
+        }
+    }
+
+    /// Numbers the figures of every page of the study, across its sections,
+    /// and adds them to `refs`.
+    pub(crate) fn add_figure_refs(&self, refs: &mut FigureRefs) {
+        for study_page in &self.pages {
+            let figures = study_page.sections.iter().flat_map(|s| &s.figures);
+            for (number, figure) in figures.enumerate() {
+                refs.figures
+                    .insert(figure.id.clone(), (study_page.file.clone(), number + 1));
+            }
+        }
+    }
+
+    /// Buttons of the top bar of every page, which links to every other page,
+    /// then to the external links.
+    fn top_bar_buttons(&self) -> Vec<TopBarButton<'_>> {
+        self.pages
+            .iter()
+            .map(|page| TopBarButton {
+                label: &page.nav_title,
+                icon: &page.icon,
+                link: &page.file,
+            })
+            .chain(self.links.iter().map(|link| TopBarButton {
+                label: &link.label,
+                icon: &link.icon,
+                link: &link.href,
+            }))
+            .collect()
+    }
+
+    /// Builds and publishes every page of the study.
+    pub(crate) fn build(&self, refs: &FigureRefs, config: &SiteConfig) -> Result<(), Box<dyn Error>> {
+        let buttons = self.top_bar_buttons();
+
+        // Every data file of the study, with what it holds, for its bundle.
+        let bundle_path = format!("{}/{}.zip", FOLDER_DATA, titlefy_to_page_title(&self.title));
+        let mut data_files = Vec::<(String, String)>::new();
+        let mut plotly_versions = Vec::<String>::new();
+
+        for study_page in &self.pages {
+            let mut page = Page::new_without_background(&self.title, CSS::Science, config);
+            page.add_simple_top_bar(&buttons, Some(&study_page.nav_title));
+
+            {
+                let mut about = page.buf.div().attr(
+                    "class='blank-container-square-half blank-background page-ultra-wide-treatment'",
+                );
+                write!(about.h1(), "{}", study_page.heading)?;
+                write!(about.h4(), "{}", refs.resolve_html(&study_page.about))?;
+                if let Some(data_link) = &self.data_link {
+                    write!(
+                        about.a().attr(format!("href='{}'", data_link).as_ref()).h4(),
+                        "{}",
+                        data_link
+                    )?;
+                }
+                write!(
+                    about
+                        .a()
+                        .attr(format!("class='btn btn-primary' href='{}' download", bundle_path).as_ref()),
+                    "<i class='fa-solid fa-file-zipper'></i> Download all data"
+                )?;
+            }
+
+            // Index of the figures of the page, linking to their anchors.
+            {
+                let mut index = page
+                    .buf
+                    .div()
+                    .attr("class='blank-container-square-title page-ultra-wide-treatment'");
+                write!(index.h1(), "Figures")?;
+                let mut list = index.ul().attr("class='figure-index'");
+                let figures = study_page.sections.iter().flat_map(|s| &s.figures);
+                for (number, figure) in figures.enumerate() {
+                    let caption = refs.resolve_plain(&figure.caption);
+                    let summary = match caption.split_once(". ") {
+                        Some((first_sentence, _)) => first_sentence,
+                        _ => caption.trim_end_matches('.'),
+                    };
+                    write!(
+                        list.li().a().attr(format!("href='#{}'", figure.id).as_ref()),
+                        "<b>Figure {}:</b> {}.",
+                        number + 1,
+                        summary
+                    )?;
+                }
+            }
+
+            // Figures are numbered across the sections of the page, and every
+            // other figure gets a background.
+            let mut figure_number = 0;
+            for section in &study_page.sections {
+                {
+                    let mut title = page
+                        .buf
+                        .div()
+                        .attr("class='blank-container-square-title page-ultra-wide-treatment'");
+                    write!(title.h1(), "{}", section.title)?;
+                }
+
+                for figure in &section.figures {
+                    figure_number += 1;
+
+                    let mut container = page.buf.div().attr(
+                        format!(
+                            "class='{} {}page-ultra-wide-treatment' id='{}'",
+                            figure.size.class(),
+                            if figure_number % 2 == 0 { "blank-background " } else { "" },
+                            figure.id
+                        )
+                        .as_ref(),
+                    );
+                    write!(
+                        container.h4(),
+                        "<b>Figure {number}:</b> {} <a class='figure-permalink' href='#{id}' aria-label='Permalink to Figure {number}'><i class='fa-solid fa-link'></i></a>",
+                        refs.resolve_html(&figure.caption),
+                        number = figure_number,
+                        id = figure.id
+                    )?;
+                    let paths = match (&figure.path, &figure.chart) {
+                        (Some(path), None) => {
+                            add_figure_to_node(&mut container, &mut page.plotly, path, figure.loading.unwrap_or(self.loading))?
+                        }
+                        (None, Some(chart)) => add_chart_to_node(&mut container, &figure.id, chart)?,
+                        _ => return Err("Figures need either a path or a chart.".into()),
+                    };
+                    let description = format!(
+                        "Figure {} of {}: {}",
+                        figure_number,
+                        study_page.file,
+                        refs.resolve_plain(&figure.caption)
+                    );
+                    data_files.extend(paths.into_iter().map(|p| (p, description.clone())));
+                }
+
+                for table in &section.tables {
+                    let data_table = DataTable::from_file(&table.path)?;
+
+                    // Readers can download the table as it is shown.
+                    let csv_path = format!("{}/{}.csv", FOLDER_DATA, table.id);
+                    fs::create_dir_all(FOLDER_DATA)?;
+                    fs::write(&csv_path, data_table.to_csv())?;
+                    data_files.push((
+                        csv_path.clone(),
+                        format!("Table of {}: {}", study_page.file, refs.resolve_plain(&table.caption)),
+                    ));
+
+                    let mut container = page.buf.div().attr(
+                        format!(
+                            "class='blank-container-square-title page-ultra-wide-treatment' id='{}'",
+                            table.id
+                        )
+                        .as_ref(),
+                    );
+                    if !page.data_table_script {
+                        DataTable::write_script(&mut container);
+                        page.data_table_script = true;
+                    }
+                    data_table.write(
+                        &mut container,
+                        &format!("{}-table", table.id),
+                        &refs.resolve_html(&table.caption),
+                        &csv_path,
+                    );
+                }
+            }
+
+            if let Some(version) = &page.plotly.version {
+                if !plotly_versions.contains(version) {
+                    vendor_plotly_runtime(version)?;
+                    plotly_versions.push(version.clone());
+                }
+            }
+            page.publish(&study_page.file);
+        }
+
+        self.write_data_bundle(&bundle_path, &data_files)
+    }
+
+    /// Writes a zip with every data file of the study, a README describing
+    /// them and a `SHA256SUMS` manifest to check them with `sha256sum -c`.
+    /// Everything is in a folder named after the study, and files keep their
+    /// path, so that neither bundles nor files of the same name clash.
+    fn write_data_bundle(&self, bundle_path: &str, data_files: &[(String, String)]) -> Result<(), Box<dyn Error>> {
+        println!("Writing data bundle {:?}", bundle_path);
+        if let Some(bundle_folder) = Path::new(bundle_path).parent() {
+            fs::create_dir_all(bundle_folder)?;
+        }
+        let mut zip = ZipWriter::new(File::create(bundle_path)?);
+        let folder = titlefy_to_page_title(&self.title);
+        let options = FileOptions::default();
+
+        let mut readme = format!("# {}\n\nData of every figure and table of the study", self.title);
+        if let Some(data_link) = &self.data_link {
+            write!(readme, ", whose source is at {}", data_link)?;
+        }
+        write!(readme, ".\n\nCheck the files with `sha256sum -c SHA256SUMS`.\n\n## Files\n\n")?;
+        let mut checksums = String::new();
+
+        for (path, description) in data_files {
+            let contents = fs::read(path)?;
+
+            zip.start_file(format!("{}/{}", folder, path), options)?;
+            zip.write_all(&contents)?;
+
+            writeln!(readme, "- `{}`: {}", path, strip_tags(description))?;
+            writeln!(checksums, "{:x}  {}", Sha256::digest(&contents), path)?;
+        }
+
+        zip.start_file(format!("{}/README.md", folder), options)?;
+        zip.write_all(readme.as_bytes())?;
+        zip.start_file(format!("{}/SHA256SUMS", folder), options)?;
+        zip.write_all(checksums.as_bytes())?;
+        zip.finish()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn study_from_file() {
+        let study = Study::from_file("studies/sms/study.toml").unwrap();
+        assert_eq!(study.title, "Systematic Mapping Study");
+        assert_eq!(study.loading, FigureLoading::Lazy);
+
+        let files = study.pages.iter().map(|p| p.file.as_str()).collect::<Vec<_>>();
+        assert_eq!(files, ["smsquality.html", "sms.html", "smspairwise.html"]);
+        assert_eq!(study.pages[0].heading, "Quality Assessment");
+        assert_eq!(study.pages[0].sections[0].title, "Charts");
+        assert_eq!(study.pages[0].sections[1].tables[0].id, "selected_studies");
+
+        let figures = study.pages.iter().flat_map(|p| &p.sections).flat_map(|s| &s.figures);
+        assert_eq!(figures.clone().count(), 45);
+        let first = figures.clone().next().unwrap();
+        assert_eq!(first.id, "quality_sstotal_vs_srtotal");
+        assert_eq!(first.path.as_deref(), Some("sms_figures/quality_sstotal_vs_srtotal.html"));
+
+        // The D060 chart is drawn from the table, not from a Plotly export.
+        let d060 = figures.clone().find(|f| f.id == "individual_D060").unwrap();
+        assert!(d060.path.is_none());
+        assert!(d060.chart.is_some());
+    }
+
+    #[test]
+    fn figure_loading_overrides_study_loading() {
+        let study: Study = toml::from_str(
+            r#"
+            title = "Study"
+            loading = "lazy"
+
+            [[pages]]
+            file = "study.html"
+            nav_title = "Study"
+            icon = "fa-check"
+            heading = "Study"
+            about = "About"
+
+            [[pages.sections]]
+            title = "Charts"
+
+            [[pages.sections.figures]]
+            id = "lazy"
+            path = "lazy.html"
+            caption = "Lazy."
+
+            [[pages.sections.figures]]
+            id = "eager"
+            path = "eager.html"
+            caption = "Eager."
+            loading = "eager"
+            "#,
+        )
+        .unwrap();
+
+        let loadings = study.pages[0].sections[0]
+            .figures
+            .iter()
+            .map(|f| f.loading.unwrap_or(study.loading))
+            .collect::<Vec<_>>();
+        assert_eq!(loadings, [FigureLoading::Lazy, FigureLoading::Eager]);
+    }
+
+    #[test]
+    fn top_bar_marks_current_page() {
+        let study = Study::from_file("studies/sms/study.toml").unwrap();
+        let buttons = study.top_bar_buttons();
+        assert_eq!(buttons.len(), study.pages.len() + study.links.len());
+
+        let mut page = Page::new_without_background(&study.title, CSS::Science, &SiteConfig::default());
+        page.add_simple_top_bar(&buttons, Some(&study.pages[1].nav_title));
+        let html = page.buf.finish();
+
+        // Only the desktop and the mobile buttons of the current page are marked.
+        assert_eq!(html.matches("aria-current='page'").count(), 2);
+        let (_, current) = html.split_once("<button class='btn btn-top-bar' aria-current='page'>").unwrap();
+        assert!(current.trim_start().starts_with(&study.pages[1].nav_title));
+        assert!(html.contains(&format!(
+            "<button class='btn btn-top-bar-mobile fa-solid {}' aria-current='page'>",
+            study.pages[1].icon
+        )));
+    }
+
+    #[test]
+    fn data_bundle_has_readme_and_checksums() {
+        let study: Study = toml::from_str(
+            r#"
+            title = "Systematic Mapping Study"
+            data_link = "https://github.com/cfpgomes/sms-article-2"
+            pages = []
+            "#,
+        )
+        .unwrap();
+        let folder = std::env::temp_dir().join("personal-website-data-bundle");
+        let _ = fs::remove_dir_all(&folder);
+        let bundle_path = folder.join("bundle.zip");
+        let data_files = [
+            (
+                String::from("sms_figures/table.html"),
+                String::from("Table of sms.html: <b>Selected</b> studies"),
+            ),
+            (String::from("tests/fixtures/chart.csv"), String::from("Figure 1 of sms.html: Chart")),
+        ];
+        study
+            .write_data_bundle(bundle_path.to_str().unwrap(), &data_files)
+            .unwrap();
+
+        let mut zip = zip::ZipArchive::new(File::open(&bundle_path).unwrap()).unwrap();
+        let mut names = zip.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "systematic-mapping-study/README.md",
+                "systematic-mapping-study/SHA256SUMS",
+                "systematic-mapping-study/sms_figures/table.html",
+                "systematic-mapping-study/tests/fixtures/chart.csv",
+            ]
+        );
+
+        let read = |zip: &mut zip::ZipArchive<File>, name: &str| {
+            let mut contents = Vec::new();
+            std::io::Read::read_to_end(&mut zip.by_name(name).unwrap(), &mut contents).unwrap();
+            contents
+        };
+        let readme = String::from_utf8(read(&mut zip, "systematic-mapping-study/README.md")).unwrap();
+        assert!(readme.starts_with("# Systematic Mapping Study\n"));
+        assert!(readme.contains("whose source is at https://github.com/cfpgomes/sms-article-2."));
+        assert!(readme.contains("- `sms_figures/table.html`: Table of sms.html: Selected studies\n"));
+
+        let checksums = String::from_utf8(read(&mut zip, "systematic-mapping-study/SHA256SUMS")).unwrap();
+        let table = fs::read("sms_figures/table.html").unwrap();
+        assert_eq!(checksums.lines().count(), 2);
+        assert_eq!(
+            checksums.lines().next().unwrap(),
+            format!("{:x}  sms_figures/table.html", Sha256::digest(&table))
+        );
+        assert_eq!(read(&mut zip, "systematic-mapping-study/sms_figures/table.html"), table);
+    }
+
+    #[test]
+    fn figure_refs_resolve() {
+        let mut refs = FigureRefs::default();
+        Study::from_file("studies/sms/study.toml")
+            .unwrap()
+            .add_figure_refs(&mut refs);
+        assert_eq!(
+            refs.resolve_html("See @fig:quality_sstotal_vs_sjr."),
+            "See <a href='smsquality.html#quality_sstotal_vs_sjr'>Figure 2</a>."
+        );
+        assert_eq!(
+            refs.resolve_markdown("(@fig:quality_sstotal_vs_srtotal)"),
+            "([Figure 1](smsquality.html#quality_sstotal_vs_srtotal))"
+        );
+    }
+
+    #[test]
+    fn figure_refs_keep_unknown_references() {
+        let refs = FigureRefs::default();
+        assert_eq!(refs.resolve_html("See @fig:missing-id, or @fig:"), "See @fig:missing-id, or @fig:");
+    }
+}
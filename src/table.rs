@@ -0,0 +1,451 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{escape_html, unescape_html};
+
+/// Struct that represents a table of data, read from a pandas
+/// `DataFrame.to_html()` dump, a CSV file or a JSON file, and rendered as an
+/// accessible table that readers can sort, filter and download.
+#[derive(Debug)]
+pub(crate) struct DataTable {
+    columns: Vec<String>,
+    rows: Vec<Vec<DataCell>>,
+}
+
+/// Cell of a `DataTable`, with its plain text and, when the cell is a link
+/// (e.g. a DOI), where it links to.
+#[derive(Debug)]
+struct DataCell {
+    text: String,
+    link: Option<String>,
+}
+
+impl DataCell {
+    fn from_text(text: &str) -> Self {
+        Self {
+            // Missing values are left empty instead of showing pandas' NaN.
+            text: if text == "NaN" { String::new() } else { text.to_string() },
+            link: None,
+        }
+    }
+
+    fn from_html(html: &str) -> Self {
+        let link = html
+            .split_once("href=\"")
+            .and_then(|(_, href)| href.split_once('"'))
+            .map(|(href, _)| unescape_html(href));
+
+        let mut cell = Self::from_text(&unescape_html(strip_tags(html).trim()));
+        cell.link = link;
+        cell
+    }
+}
+
+impl DataTable {
+    pub(crate) fn from_file(table_path: &str) -> Result<Self, &'static str> {
+        let contents = match fs::read(table_path) {
+            Ok(c) => decode_text(c),
+            _ => return Err("Couldn't read table file."),
+        };
+
+        match Path::new(table_path).extension().and_then(|e| e.to_str()) {
+            Some("html") => Self::from_pandas_html(&contents),
+            Some("csv") => Self::from_csv(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err("Unknown table file extension."),
+        }
+    }
+
+    /// Reads a table exported with pandas' `DataFrame.to_html()`, dropping
+    /// its index column, which has an empty header cell and header cells in
+    /// the body, unless it was exported with `index=False`.
+    pub(crate) fn from_pandas_html(html: &str) -> Result<Self, &'static str> {
+        let (head, body) = match html.split_once("</thead>") {
+            Some(s) => s,
+            _ => return Err("Couldn't find table head."),
+        };
+
+        let header = match html_rows(head).into_iter().next() {
+            Some(row) => row,
+            _ => return Err("Couldn't find table columns."),
+        };
+        let first_body_cell = body
+            .split_once("<tr")
+            .and_then(|(_, row)| row.find("<t").map(|start| &row[start..]));
+        let has_index = header.first().is_some_and(|c| strip_tags(c).trim().is_empty())
+            || first_body_cell.is_some_and(|cell| cell.starts_with("<th"));
+        let skip = usize::from(has_index);
+
+        let columns = header
+            .iter()
+            .skip(skip)
+            .map(|c| unescape_html(strip_tags(c).trim()))
+            .collect::<Vec<_>>();
+
+        let rows = html_rows(body)
+            .into_iter()
+            .map(|row| row.iter().skip(skip).map(|c| DataCell::from_html(c)).collect())
+            .collect();
+
+        Ok(Self { columns, rows })
+    }
+
+    pub(crate) fn from_csv(csv: &str) -> Result<Self, &'static str> {
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let columns = match reader.headers() {
+            Ok(h) => h.iter().map(|c| c.to_string()).collect(),
+            _ => return Err("Couldn't read CSV header."),
+        };
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            match record {
+                Ok(r) => rows.push(r.iter().map(DataCell::from_text).collect()),
+                _ => return Err("Couldn't read CSV record."),
+            }
+        }
+
+        Ok(Self { columns, rows })
+    }
+
+    /// Reads either an array of records (pandas' `orient='records'`) or an
+    /// object with `columns` and `data` (pandas' `orient='split'`).
+    pub(crate) fn from_json(json: &str) -> Result<Self, &'static str> {
+        let value: Value = match serde_json::from_str(json) {
+            Ok(v) => v,
+            _ => return Err("Couldn't parse table JSON."),
+        };
+
+        let text = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            v => v.to_string(),
+        };
+
+        match value {
+            Value::Array(records) => {
+                let mut columns = Vec::<String>::new();
+                for record in &records {
+                    if let Value::Object(fields) = record {
+                        for key in fields.keys() {
+                            if !columns.contains(key) {
+                                columns.push(key.clone());
+                            }
+                        }
+                    }
+                }
+
+                let rows = records
+                    .iter()
+                    .map(|record| {
+                        columns
+                            .iter()
+                            .map(|c| DataCell::from_text(&record.get(c).map(text).unwrap_or_default()))
+                            .collect()
+                    })
+                    .collect();
+
+                Ok(Self { columns, rows })
+            }
+            Value::Object(split) => {
+                let columns = match split.get("columns") {
+                    Some(Value::Array(c)) => c.iter().map(text).collect(),
+                    _ => return Err("Couldn't find table columns."),
+                };
+                let rows = match split.get("data") {
+                    Some(Value::Array(d)) => d
+                        .iter()
+                        .map(|row| match row {
+                            Value::Array(cells) => {
+                                cells.iter().map(|c| DataCell::from_text(&text(c))).collect()
+                            }
+                            _ => Vec::new(),
+                        })
+                        .collect(),
+                    _ => return Err("Couldn't find table data."),
+                };
+
+                Ok(Self { columns, rows })
+            }
+            _ => Err("Unknown table JSON layout."),
+        }
+    }
+
+    pub(crate) fn from_columns(columns: Vec<String>, rows: Vec<Vec<String>>) -> Self {
+        Self {
+            columns,
+            rows: rows
+                .iter()
+                .map(|row| row.iter().map(|c| DataCell::from_text(c)).collect())
+                .collect(),
+        }
+    }
+
+    /// Returns the text of every cell of the column called `name`.
+    pub(crate) fn column(&self, name: &str) -> Option<Vec<&str>> {
+        let i = self.columns.iter().position(|c| c == name)?;
+        Some(
+            self.rows
+                .iter()
+                .map(|row| row.get(i).map(|c| c.text.as_str()).unwrap_or(""))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn to_csv(&self) -> String {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.columns).unwrap();
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|c| &c.text)).unwrap();
+        }
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    /// Returns the rows as an array of records, with numbers as numbers and
+    /// empty cells as nulls.
+    pub(crate) fn to_json(&self) -> String {
+        let records = self
+            .rows
+            .iter()
+            .map(|row| {
+                let fields = self.columns.iter().zip(row).map(|(column, cell)| {
+                    let value = match cell.text.parse::<f64>() {
+                        _ if cell.text.is_empty() => Value::Null,
+                        Ok(n) if n.fract() == 0. && n.abs() < 1e15 => Value::from(n as i64),
+                        Ok(n) if n.is_finite() => Value::from(n),
+                        _ => Value::from(cell.text.as_str()),
+                    };
+                    (column.clone(), value)
+                });
+                Value::Object(fields.collect())
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_string_pretty(&records).unwrap()
+    }
+
+    /// Writes the table with a filter above it and a link to `csv_path`.
+    pub(crate) fn write(&self, w: &mut dyn Write, id: &str, caption: &str, csv_path: &str) {
+        write!(
+            w,
+            "<div class='data-table-controls'>\
+            <input class='form-input data-table-filter' type='search' placeholder='Filter rows...' \
+            aria-label='Filter rows of the table' aria-controls='{id}' oninput='filterDataTable(this)'>\
+            <a class='btn btn-link' href='{}' download><i class='fa-solid fa-download'></i> Download CSV</a>\
+            </div>\
+            <div class='data-table-scroll'>\
+            <table class='table table-striped table-hover data-table' id='{id}'>\
+            <caption>{}</caption><thead><tr>",
+            csv_path,
+            caption,
+            id = id,
+        )
+        .unwrap();
+
+        for column in &self.columns {
+            write!(
+                w,
+                "<th scope='col'><button class='btn btn-link data-table-sort' onclick='sortDataTable(this)'>{}</button></th>",
+                escape_html(column)
+            )
+            .unwrap();
+        }
+        write!(w, "</tr></thead><tbody>").unwrap();
+        self.write_rows(w);
+        write!(w, "</tbody></table></div>").unwrap();
+    }
+
+    /// Writes the table alone, without the controls that need scripts.
+    pub(crate) fn write_plain(&self, w: &mut dyn Write, id: &str, caption: &str) {
+        write!(
+            w,
+            "<div class='data-table-scroll'>\
+            <table class='table table-striped data-table' id='{}'>\
+            <caption>{}</caption><thead><tr>",
+            id, caption
+        )
+        .unwrap();
+        for column in &self.columns {
+            write!(w, "<th scope='col'>{}</th>", escape_html(column)).unwrap();
+        }
+        write!(w, "</tr></thead><tbody>").unwrap();
+        self.write_rows(w);
+        write!(w, "</tbody></table></div>").unwrap();
+    }
+
+    /// Writes every row, with the first column as the header of the row.
+    fn write_rows(&self, w: &mut dyn Write) {
+        for row in &self.rows {
+            write!(w, "<tr>").unwrap();
+            for (i, cell) in row.iter().enumerate() {
+                let tag = if i == 0 { "th scope='row'" } else { "td" };
+                let text = escape_html(&cell.text);
+                match &cell.link {
+                    Some(link) => write!(
+                        w,
+                        "<{}><a href='{}' target='_blank'>{}</a></{}>",
+                        tag,
+                        escape_html(link),
+                        text,
+                        &tag[..2]
+                    ),
+                    None => write!(w, "<{}>{}</{}>", tag, text, &tag[..2]),
+                }
+                .unwrap();
+            }
+            write!(w, "</tr>").unwrap();
+        }
+    }
+
+    /// Writes the scripts used by every table of a page.
+    pub(crate) fn write_script(w: &mut dyn Write) {
+        write!(w, "<script type='text/javascript'>
+        function sortDataTable(button)
+        {{
+            var header = button.closest('th');
+            var table = header.closest('table');
+            var column = Array.prototype.indexOf.call(header.parentNode.children, header);
+            var ascending = header.getAttribute('aria-sort') !== 'ascending';
+
+            table.querySelectorAll('thead th').forEach(function(th) {{
+                th.removeAttribute('aria-sort');
+            }});
+            header.setAttribute('aria-sort', ascending ? 'ascending' : 'descending');
+
+            var body = table.tBodies[0];
+            var rows = Array.prototype.slice.call(body.rows);
+            rows.sort(function(a, b) {{
+                var x = a.cells[column].textContent.trim();
+                var y = b.cells[column].textContent.trim();
+                var order = (x !== '' && y !== '' && !isNaN(x) && !isNaN(y)) ? x - y : x.localeCompare(y);
+                return ascending ? order : -order;
+            }});
+            rows.forEach(function(row) {{
+                body.appendChild(row);
+            }});
+        }}
+
+        function filterDataTable(input)
+        {{
+            var query = input.value.toLowerCase();
+            var table = document.getElementById(input.getAttribute('aria-controls'));
+            Array.prototype.forEach.call(table.tBodies[0].rows, function(row) {{
+                row.hidden = query !== '' && row.textContent.toLowerCase().indexOf(query) === -1;
+            }});
+        }}
+        </script>").unwrap();
+    }
+}
+
+/// Decodes UTF-8, falling back to Windows-1252, which is what pandas writes
+/// on Windows when the encoding isn't given.
+fn decode_text(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e
+            .into_bytes()
+            .into_iter()
+            .map(|b| match b {
+                0x85 => '…',
+                0x91 => '‘',
+                0x92 => '’',
+                0x93 => '“',
+                0x94 => '”',
+                0x96 => '–',
+                0x97 => '—',
+                b => b as char,
+            })
+            .collect(),
+    }
+}
+
+/// Returns the inner HTML of the cells of every row in `html`.
+fn html_rows(html: &str) -> Vec<Vec<&str>> {
+    let mut rows = Vec::new();
+    let mut rest = html;
+    while let Some((_, row)) = rest.split_once("<tr") {
+        let (row, after) = row.split_once("</tr>").unwrap_or((row, ""));
+
+        let mut cells = Vec::new();
+        let mut cell_rest = row;
+        while let Some(start) = cell_rest.find("<t") {
+            let cell = &cell_rest[start..];
+            let close = if cell.starts_with("<th") { "</th>" } else { "</td>" };
+            match (cell.split_once('>'), cell.find(close)) {
+                (Some((tag, _)), Some(end)) => {
+                    cells.push(&cell[tag.len() + 1..end]);
+                    cell_rest = &cell[end + close.len()..];
+                }
+                _ => break,
+            }
+        }
+
+        rows.push(cells);
+        rest = after;
+    }
+    rows
+}
+
+pub(crate) fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_table_from_pandas_html() {
+        let table = DataTable::from_file("sms_figures/table.html").unwrap();
+        let csv = table.to_csv();
+        assert!(csv.starts_with("Title,DOI,SS00,SR00,"));
+        assert!(csv.lines().nth(1).unwrap().starts_with("Quantum Shuttle: traffic navigation with Quantum computing,"));
+        assert_eq!(csv.lines().count(), 150);
+    }
+
+    #[test]
+    fn data_table_from_pandas_html_without_index() {
+        let html = "<table border=\"1\" class=\"dataframe\">
+  <thead>
+    <tr style=\"text-align: right;\">
+      <th>Year</th>
+      <th>Count</th>
+    </tr>
+  </thead>
+  <tbody>
+    <tr>
+      <td>2021</td>
+      <td>29</td>
+    </tr>
+    <tr>
+      <td>2022</td>
+      <td>5</td>
+    </tr>
+  </tbody>
+</table>";
+        let table = DataTable::from_pandas_html(html).unwrap();
+        assert_eq!(table.columns, ["Year", "Count"]);
+        assert_eq!(table.to_csv(), "Year,Count\n2021,29\n2022,5\n");
+    }
+
+    #[test]
+    fn data_table_from_pandas_html_with_row_headers() {
+        let html = "<table><thead><tr><th>Year</th><th>Count</th></tr></thead>\
+            <tbody><tr><th>2021</th><td>29</td></tr></tbody></table>";
+        let table = DataTable::from_pandas_html(html).unwrap();
+        assert_eq!(table.columns, ["Count"]);
+        assert_eq!(table.to_csv(), "Count\n29\n");
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use html_builder::*;
+
+use serde::Deserialize;
+
+use super::images::{Backgrounds, ResponsiveImage};
+use super::site::SiteConfig;
+use super::{escape_html, titlefy_to_page_title, Page, Publication, CSS};
+
+/// Smallest and largest font size, in rem, of the tags of the tag cloud.
+const TAG_CLOUD_SIZES: (f32, f32) = (0.8, 1.8);
+
+/// Struct that represents how a tag is shown, as described by its entry in
+/// `tags.toml`. Its `aliases` are other tags that mean the same, and are
+/// merged into it.
+#[derive(Debug, Default, Deserialize)]
+struct TagInfo {
+    name: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+/// Struct that represents every tag that `tags.toml` describes, by the tag
+/// publications should use.
+#[derive(Debug, Default)]
+pub(crate) struct Taxonomy {
+    tags: BTreeMap<String, TagInfo>,
+    /// Tag of every lowercase tag and alias.
+    canonical: HashMap<String, String>,
+}
+
+impl Taxonomy {
+    /// Reads the tags in `tags_path`, if the file exists.
+    pub(crate) fn from_file(tags_path: &str) -> Result<Self, &'static str> {
+        if !Path::new(tags_path).exists() {
+            return Ok(Self::default());
+        }
+
+        println!("Reading tags file {:?}", tags_path);
+        let tags: BTreeMap<String, TagInfo> = match fs::read_to_string(tags_path) {
+            Ok(t) => match toml::from_str(&t) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("{}", e);
+                    return Err("Couldn't parse tags file.");
+                }
+            },
+            _ => return Err("Couldn't read tags file."),
+        };
+
+        let mut canonical = HashMap::new();
+        for (tag, info) in &tags {
+            for alias in std::iter::once(tag).chain(&info.aliases) {
+                if canonical.insert(alias.to_lowercase(), tag.clone()).is_some() {
+                    println!("Tag {:?} is defined more than once.", alias);
+                    return Err("Couldn't tell tags apart.");
+                }
+            }
+        }
+
+        Ok(Self { tags, canonical })
+    }
+
+    /// Tag that `tag` is, or an alias of. Tags are matched regardless of case.
+    pub(crate) fn canonical(&self, tag: &str) -> String {
+        match self.canonical.get(&tag.to_lowercase()) {
+            Some(t) => t.clone(),
+            None => tag.to_string(),
+        }
+    }
+
+    /// Replaces the tags of `post` by the tags they are aliases of, once each.
+    pub(crate) fn canonicalize(&self, post: &mut Publication) {
+        let mut tags = Vec::<String>::new();
+        for tag in post.tags.iter().filter(|t| !t.is_empty()) {
+            let tag = self.canonical(tag);
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        post.tags = tags;
+    }
+
+    pub(crate) fn name<'a>(&'a self, tag: &'a str) -> &'a str {
+        match self.tags.get(tag).and_then(|t| t.name.as_deref()) {
+            Some(name) => name,
+            None => tag,
+        }
+    }
+
+    fn description(&self, tag: &str) -> Option<&str> {
+        self.tags.get(tag).and_then(|t| t.description.as_deref())
+    }
+}
+
+pub(crate) fn tag_page_path(tag: &str) -> String {
+    format!("tags-{}.html", titlefy_to_page_title(tag))
+}
+
+/// Writes the chips of `tags`, linking to their pages.
+pub(crate) fn write_tag_chips(w: &mut dyn Write, tags: &[String], taxonomy: &Taxonomy) {
+    write!(w, "<div class='tag-chips'>").unwrap();
+    for tag in tags {
+        write!(
+            w,
+            "<a class='chip' href='{}'>{}</a>",
+            tag_page_path(tag),
+            escape_html(taxonomy.name(tag))
+        )
+        .unwrap();
+    }
+    write!(w, "</div>").unwrap();
+}
+
+/// Builds the page of every tag of `posts`, listing the posts with it, and
+/// the tag index, where tags are larger the more posts have them.
+pub(crate) fn build_tag_pages(
+    posts: &[Publication],
+    taxonomy: &Taxonomy,
+    config: &SiteConfig,
+    backgrounds: &Backgrounds,
+    profile_pic: &ResponsiveImage,
+) -> Result<(), Box<dyn Error>> {
+    // Posts of every tag, in the order of `posts`, which is the most recent
+    // first.
+    let mut tagged = BTreeMap::<&str, Vec<&Publication>>::new();
+    for post in posts {
+        for tag in &post.tags {
+            tagged.entry(tag).or_default().push(post);
+        }
+    }
+
+    for (tag, tag_posts) in &tagged {
+        let mut page = Page::new(taxonomy.name(tag), CSS::Science, config, backgrounds);
+        page.add_site_top_bar(profile_pic, Some("Publications"));
+
+        {
+            let mut container = page
+                .buf
+                .div()
+                .attr("class='blank-container page-ultra-wide-treatment'");
+            write!(container.h1(), "{}", escape_html(taxonomy.name(tag)))?;
+            if let Some(description) = taxonomy.description(tag) {
+                write!(container.h4(), "{}", escape_html(description))?;
+            }
+
+            let mut list = container.ul().attr("class='tag-publications'");
+            for post in tag_posts {
+                let mut item = list.li();
+                write!(
+                    item.a().attr(format!("href='{}'", post.page_path()).as_str()),
+                    "{}",
+                    escape_html(&post.title)
+                )?;
+                write!(item.span().attr("class='date_color'"), " {}", post.date)?;
+            }
+
+            write!(
+                container.a().attr("class='btn btn-link' href='tags.html'"),
+                "All tags"
+            )?;
+        }
+
+        page.add_footer();
+        page.publish(&tag_page_path(tag));
+    }
+
+    let mut page = Page::new("Tags", CSS::Science, config, backgrounds);
+    page.add_site_top_bar(profile_pic, Some("Publications"));
+
+    {
+        let mut container = page
+            .buf
+            .div()
+            .attr("class='blank-container page-ultra-wide-treatment'");
+        write!(container.h1(), "Tags")?;
+
+        let mut names = tagged
+            .iter()
+            .map(|(tag, tag_posts)| (*tag, taxonomy.name(tag), tag_posts.len()))
+            .collect::<Vec<_>>();
+        names.sort_by_key(|(_, name, _)| name.to_lowercase());
+
+        let most = names.iter().map(|(_, _, count)| *count).max().unwrap_or(1);
+        let (smallest, largest) = TAG_CLOUD_SIZES;
+
+        let mut cloud = container.div().attr("class='tag-cloud'");
+        for (tag, name, count) in names {
+            let size = match most {
+                1 => smallest,
+                _ => smallest + (largest - smallest) * (count - 1) as f32 / (most - 1) as f32,
+            };
+            write!(
+                cloud.a().attr(
+                    format!(
+                        "class='chip' href='{}' style='font-size:{:.2}rem' aria-label='{}, {} publications'",
+                        tag_page_path(tag),
+                        size,
+                        escape_html(name),
+                        count
+                    )
+                    .as_str()
+                ),
+                "{} <span class='tag-count'>{}</span>",
+                escape_html(name),
+                count
+            )?;
+        }
+    }
+
+    page.add_footer();
+    page.publish("tags.html");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+
+    #[test]
+    fn taxonomy_merges_aliases() {
+        let taxonomy = Taxonomy::from_file("tags.toml").unwrap();
+        assert_eq!(taxonomy.canonical("quantum-computing"), "QC");
+        assert_eq!(taxonomy.canonical("qc"), "QC");
+        assert_eq!(taxonomy.canonical("untagged"), "untagged");
+        assert_eq!(taxonomy.name("QC"), "Quantum Computing");
+
+        let date = NaiveDate::from_ymd_opt(2022, 5, 14).unwrap();
+        let tags = ["qc", "", "Quantum-Computing", "untagged"].map(String::from).to_vec();
+        let mut post = Publication::new("Test", date, "Text.", tags);
+        taxonomy.canonicalize(&mut post);
+        assert_eq!(post.tags, ["QC", "untagged"]);
+    }
+
+    #[test]
+    fn tag_chips_link_to_tag_pages() {
+        let taxonomy = Taxonomy::from_file("tags.toml").unwrap();
+        let mut html = String::new();
+        write_tag_chips(&mut html, &[String::from("QC"), String::from("R&D")], &taxonomy);
+        assert_eq!(
+            html,
+            "<div class='tag-chips'><a class='chip' href='tags-qc.html'>Quantum Computing</a>\
+            <a class='chip' href='tags-rd.html'>R&amp;D</a></div>"
+        );
+    }
+}